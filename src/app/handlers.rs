@@ -1,13 +1,20 @@
-use crate::models::{Alias, Snippet};
+use crate::models::{Alias, AliasKind, EnvVar, ShellFunction, Snippet};
+use crate::storage::{
+    adoptable, AliasChange, AliasStoreError, SnippetChange, SnippetStoreError, StoreChange,
+};
 use crate::ui::DialogMode;
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyModifiers};
 
-use super::state::{AppState, Focus};
+use super::state::{AppState, Focus, PendingChange, ALIAS_KIND_FIELD, LIBRARY_FIELD};
 
 pub fn handle_key(
     app: &mut AppState,
     key: crossterm::event::KeyEvent,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Messages only describe the key press that caused them.
+    app.error_message = None;
+    app.success_message = None;
+
     if app.help_visible {
         match key.code {
             KeyCode::Char('?') | KeyCode::Esc => app.toggle_help(),
@@ -27,6 +34,8 @@ pub fn handle_key(
 
 fn handle_normal_mode(app: &mut AppState, key: crossterm::event::KeyEvent) {
     match key.code {
+        KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => step(app, true),
+        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => step(app, false),
         KeyCode::Char('q') => app.quit(),
         KeyCode::Char('?') => app.toggle_help(),
         KeyCode::Tab => app.next_tab(),
@@ -43,11 +52,22 @@ fn handle_normal_mode(app: &mut AppState, key: crossterm::event::KeyEvent) {
         KeyCode::Char('e') => handle_edit(app),
         KeyCode::Char('d') => handle_delete(app),
         KeyCode::Char('u') => handle_update(app),
-        KeyCode::Esc => {
-            if app.focus == Focus::Search {
-                app.unfocus_search();
-            }
+        KeyCode::Char('i') if app.current_tab == crate::ui::Tab::Aliases => {
+            app.show_import_dialog()
+        }
+        KeyCode::Char('l') if app.current_tab == crate::ui::Tab::Snippets => {
+            app.cycle_library_filter()
+        }
+        KeyCode::Char('m') if app.current_tab == crate::ui::Tab::Snippets => {
+            app.show_transfer_snippet_dialog(false)
         }
+        KeyCode::Char('c') if app.current_tab == crate::ui::Tab::Snippets => {
+            app.show_transfer_snippet_dialog(true)
+        }
+        KeyCode::Char('v') if app.current_tab == crate::ui::Tab::History => {
+            app.show_promote_history_dialog(true)
+        }
+        KeyCode::Esc if app.focus == Focus::Search => app.unfocus_search(),
         _ => {}
     }
 }
@@ -80,7 +100,6 @@ fn handle_dialog_mode(app: &mut AppState, key: crossterm::event::KeyEvent) {
         match key.code {
             KeyCode::Esc => {
                 app.close_dialog();
-                return;
             }
             KeyCode::Tab => {
                 dialog.next_field();
@@ -88,19 +107,41 @@ fn handle_dialog_mode(app: &mut AppState, key: crossterm::event::KeyEvent) {
             KeyCode::BackTab => {
                 dialog.prev_field();
             }
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => {
+                dialog.move_field(-1);
+            }
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => {
+                dialog.move_field(1);
+            }
+            KeyCode::Up if dialog.has_preview() => dialog.scroll_preview(-1),
+            KeyCode::Down if dialog.has_preview() => dialog.scroll_preview(1),
+            KeyCode::PageUp if dialog.has_preview() => dialog.scroll_preview(-10),
+            KeyCode::PageDown if dialog.has_preview() => dialog.scroll_preview(10),
             KeyCode::Up => {
                 dialog.prev_field();
             }
             KeyCode::Down => {
                 dialog.next_field();
             }
+            KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+                if let Some(field) = dialog.current_field_mut() {
+                    field.handle_newline();
+                }
+            }
+            KeyCode::Char('j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(field) = dialog.current_field_mut() {
+                    field.handle_newline();
+                }
+            }
             KeyCode::Enter => {
                 let values = dialog.get_values();
                 let mode = dialog.mode;
                 if let Err(e) = handle_dialog_submit(app, mode, &values) {
+                    if let Some(dialog) = app.dialog.as_mut() {
+                        dialog.message = Some((e.to_string(), true));
+                    }
                     app.error_message = Some(e.to_string());
                 }
-                return;
             }
             KeyCode::Char(c) => {
                 if let Some(field) = dialog.current_field_mut() {
@@ -134,10 +175,12 @@ fn handle_dialog_mode(app: &mut AppState, key: crossterm::event::KeyEvent) {
 
 fn handle_enter(app: &mut AppState) {
     match app.current_tab {
-        crate::ui::Tab::Aliases => {}
+        crate::ui::Tab::Aliases | crate::ui::Tab::Functions => {}
         crate::ui::Tab::Snippets => {
             app.try_run_snippet();
         }
+        crate::ui::Tab::Env => app.show_path_edit_dialog(),
+        crate::ui::Tab::History => app.show_promote_history_dialog(false),
     }
 }
 
@@ -145,6 +188,9 @@ fn handle_add(app: &mut AppState) {
     match app.current_tab {
         crate::ui::Tab::Aliases => app.show_add_alias_dialog(),
         crate::ui::Tab::Snippets => app.show_add_snippet_dialog(),
+        crate::ui::Tab::Functions => app.show_add_function_dialog(),
+        crate::ui::Tab::Env => app.show_add_env_dialog(),
+        crate::ui::Tab::History => app.show_promote_history_dialog(false),
     }
 }
 
@@ -152,6 +198,9 @@ fn handle_edit(app: &mut AppState) {
     match app.current_tab {
         crate::ui::Tab::Aliases => app.show_edit_alias_dialog(),
         crate::ui::Tab::Snippets => app.show_edit_snippet_dialog(),
+        crate::ui::Tab::Functions => app.show_edit_function_dialog(),
+        crate::ui::Tab::Env => app.show_edit_env_dialog(),
+        crate::ui::Tab::History => {}
     }
 }
 
@@ -159,6 +208,9 @@ fn handle_delete(app: &mut AppState) {
     match app.current_tab {
         crate::ui::Tab::Aliases => app.show_delete_alias_dialog(),
         crate::ui::Tab::Snippets => app.show_delete_snippet_dialog(),
+        crate::ui::Tab::Functions => app.show_delete_function_dialog(),
+        crate::ui::Tab::Env => app.show_delete_env_dialog(),
+        crate::ui::Tab::History => {}
     }
 }
 
@@ -178,19 +230,47 @@ fn handle_dialog_submit(
     match mode {
         DialogMode::Add => match app.current_tab {
             crate::ui::Tab::Aliases => add_alias(app, &values_map)?,
-            crate::ui::Tab::Snippets => add_snippet(app, &values_map)?,
+            crate::ui::Tab::Snippets | crate::ui::Tab::History => add_snippet(app, &values_map)?,
+            crate::ui::Tab::Functions => add_function(app, &values_map)?,
+            crate::ui::Tab::Env => add_env_var(app, &values_map)?,
         },
         DialogMode::Edit => match app.current_tab {
             crate::ui::Tab::Aliases => edit_alias(app, &values_map)?,
             crate::ui::Tab::Snippets => edit_snippet(app, &values_map)?,
+            crate::ui::Tab::Functions => edit_function(app, &values_map)?,
+            crate::ui::Tab::Env => edit_env_var(app, &values_map)?,
+            crate::ui::Tab::History => {}
         },
         DialogMode::Delete => match app.current_tab {
             crate::ui::Tab::Aliases => delete_alias(app)?,
             crate::ui::Tab::Snippets => delete_snippet(app)?,
+            crate::ui::Tab::Functions => delete_function(app)?,
+            crate::ui::Tab::Env => delete_env_var(app)?,
+            crate::ui::Tab::History => {}
         },
         DialogMode::Run => {
             run_snippet(app, &values_map)?;
         }
+        DialogMode::Conflict => resolve_conflict(app, &values_map)?,
+        DialogMode::Review => confirm_review(app, &values_map)?,
+        DialogMode::PathEdit => edit_path_entries(app, values)?,
+        DialogMode::Import => import_aliases(app, &values_map)?,
+        DialogMode::Move => transfer_snippet(app, &values_map, false)?,
+        DialogMode::Copy => transfer_snippet(app, &values_map, true)?,
+        DialogMode::Recovery => {
+            let edit = values
+                .first()
+                .is_some_and(|(_, value)| value.trim().eq_ignore_ascii_case("y"));
+            if edit {
+                app.pending_edit = app
+                    .snippet_store
+                    .recoveries()
+                    .iter()
+                    .find(|r| r.quarantined.is_some())
+                    .cloned();
+            }
+            app.close_dialog();
+        }
         DialogMode::Update => {
             // Handle update confirmation
             let confirm_value = values
@@ -252,18 +332,20 @@ fn add_alias(
         return Err("Name and Command are required".into());
     }
 
-    let alias = Alias::new(
+    let mut alias = Alias::new(
         name.to_string(),
         command.to_string(),
         app.alias_store.source().clone(),
     )
-    .with_description(description.unwrap_or_default());
+    .with_kind(alias_kind(values)?);
+    alias.description = description;
+    alias.validate()?;
 
-    app.alias_store.add(alias)?;
-    app.mark_aliases_modified();
-    app.success_message = Some(format!("Alias '{}' added", name));
-    app.close_dialog();
-    Ok(())
+    if !confirm_shadowing(app, name) {
+        return Ok(());
+    }
+
+    submit_alias_change(app, AliasChange::Add(alias))
 }
 
 fn edit_alias(
@@ -272,6 +354,7 @@ fn edit_alias(
 ) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(old_alias) = app.selected_alias() {
         let old_name = old_alias.name.clone();
+        let adopting = old_alias.external;
         let name = values.get("Name").map(|s| s.as_str()).unwrap_or("");
         let command = values.get("Command").map(|s| s.as_str()).unwrap_or("");
         let description = values.get("Description (optional)").and_then(|s| {
@@ -286,32 +369,285 @@ fn edit_alias(
             return Err("Name and Command are required".into());
         }
 
-        let new_alias = Alias::new(
+        let mut new_alias = Alias::new(
             name.to_string(),
             command.to_string(),
             app.alias_store.source().clone(),
         )
-        .with_description(description.unwrap_or_default());
+        .with_kind(alias_kind(values)?);
+        new_alias.description = description;
+        new_alias.validate()?;
+
+        if name != old_name && !confirm_shadowing(app, name) {
+            return Ok(());
+        }
 
-        app.alias_store.update(&old_name, new_alias)?;
-        app.mark_aliases_modified();
-        app.success_message = Some(format!("Alias '{}' updated", name));
+        let change = if adopting {
+            AliasChange::Add(new_alias)
+        } else {
+            AliasChange::Update {
+                name: old_name,
+                alias: new_alias,
+            }
+        };
+        return submit_alias_change(app, change);
     }
     app.close_dialog();
     Ok(())
 }
 
+/// Warns in the dialog the first time an alias name would shadow something.
+/// Submitting again while the warning is showing goes ahead.
+fn confirm_shadowing(app: &mut AppState, name: &str) -> bool {
+    let Some(warning) = app.shadow_index.warning(name) else {
+        return true;
+    };
+    let warning = format!("{} (Enter again to save anyway)", warning);
+    let Some(dialog) = app.dialog.as_mut() else {
+        return true;
+    };
+    if matches!(&dialog.message, Some((shown, _)) if *shown == warning) {
+        return true;
+    }
+    dialog.message = Some((warning, true));
+    false
+}
+
+fn alias_kind(
+    values: &std::collections::HashMap<String, String>,
+) -> Result<AliasKind, Box<dyn std::error::Error>> {
+    let kind = values
+        .get(ALIAS_KIND_FIELD)
+        .map(|s| s.as_str())
+        .unwrap_or("");
+    AliasKind::parse(kind).ok_or_else(|| "Kind must be regular, global or suffix".into())
+}
+
 fn delete_alias(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(alias) = app.selected_alias() {
         let name = alias.name.clone();
-        app.alias_store.delete(&name)?;
-        app.mark_aliases_modified();
-        app.success_message = Some(format!("Alias '{}' deleted", name));
+        if alias.external {
+            app.external_aliases.forget(&name)?;
+            app.success_message = Some(format!("Stopped listing '{}'", name));
+            app.close_dialog();
+            return Ok(());
+        }
+        return submit_alias_change(app, AliasChange::Delete(name));
+    }
+    app.close_dialog();
+    Ok(())
+}
+
+fn add_function(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = values.get("Name").map(|s| s.as_str()).unwrap_or("");
+    let body = values.get("Body").map(|s| s.as_str()).unwrap_or("");
+    let description = values.get("Description (optional)").and_then(|s| {
+        if s.is_empty() {
+            None
+        } else {
+            Some(s.clone())
+        }
+    });
+
+    if name.is_empty() || body.trim().is_empty() {
+        return Err("Name and Body are required".into());
+    }
+
+    let mut function = ShellFunction::new(
+        name.to_string(),
+        body.to_string(),
+        app.alias_store.source().clone(),
+    );
+    function.description = description;
+    function.validate()?;
+
+    submit_alias_change(app, AliasChange::AddFunction(function))
+}
+
+fn edit_function(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(old_function) = app.selected_function() {
+        let old_name = old_function.name.clone();
+        let name = values.get("Name").map(|s| s.as_str()).unwrap_or("");
+        let body = values.get("Body").map(|s| s.as_str()).unwrap_or("");
+        let description = values.get("Description (optional)").and_then(|s| {
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.clone())
+            }
+        });
+
+        if name.is_empty() || body.trim().is_empty() {
+            return Err("Name and Body are required".into());
+        }
+
+        let mut new_function = ShellFunction::new(
+            name.to_string(),
+            body.to_string(),
+            app.alias_store.source().clone(),
+        );
+        new_function.description = description;
+        new_function.validate()?;
+
+        let change = AliasChange::UpdateFunction {
+            name: old_name,
+            function: new_function,
+        };
+        return submit_alias_change(app, change);
+    }
+    app.close_dialog();
+    Ok(())
+}
+
+fn delete_function(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(function) = app.selected_function() {
+        let name = function.name.clone();
+        return submit_alias_change(app, AliasChange::DeleteFunction(name));
+    }
+    app.close_dialog();
+    Ok(())
+}
+
+fn add_env_var(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = values.get("Name").map(|s| s.as_str()).unwrap_or("");
+    let value = values.get("Value").map(|s| s.as_str()).unwrap_or("");
+    let description = values.get("Description (optional)").and_then(|s| {
+        if s.is_empty() {
+            None
+        } else {
+            Some(s.clone())
+        }
+    });
+
+    if name.is_empty() {
+        return Err("Name is required".into());
+    }
+
+    let mut var = EnvVar::new(
+        name.to_string(),
+        value.to_string(),
+        app.alias_store.source().clone(),
+    );
+    var.description = description;
+
+    submit_alias_change(app, AliasChange::AddEnv(var))
+}
+
+fn edit_env_var(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(old_var) = app.selected_env_var() {
+        let old = old_var.clone();
+        let name = values.get("Name").map(|s| s.as_str()).unwrap_or("");
+        let value = values.get("Value").map(|s| s.as_str()).unwrap_or("");
+        let description = values.get("Description (optional)").and_then(|s| {
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.clone())
+            }
+        });
+
+        if name.is_empty() {
+            return Err("Name is required".into());
+        }
+
+        let mut new_var = EnvVar::new(
+            name.to_string(),
+            value.to_string(),
+            app.alias_store.source().clone(),
+        );
+        new_var.description = description;
+
+        let change = AliasChange::UpdateEnv { old, var: new_var };
+        return submit_alias_change(app, change);
+    }
+    app.close_dialog();
+    Ok(())
+}
+
+fn delete_env_var(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(var) = app.selected_env_var() {
+        let var = var.clone();
+        return submit_alias_change(app, AliasChange::DeleteEnv(var));
+    }
+    app.close_dialog();
+    Ok(())
+}
+
+/// Rebuilds a `PATH`-style value from the list editor, in field order.
+fn edit_path_entries(
+    app: &mut AppState,
+    values: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(old_var) = app.selected_env_var() {
+        let old = old_var.clone();
+        let mut var = old_var.clone();
+        let entries: Vec<&str> = values.iter().map(|(_, value)| value.as_str()).collect();
+        var.set_entries(&entries);
+        var.updated_at = Some(chrono::Utc::now());
+
+        return submit_alias_change(app, AliasChange::UpdateEnv { old, var });
     }
     app.close_dialog();
     Ok(())
 }
 
+/// Shows the rc file diff for review first when that is enabled, otherwise
+/// saves right away.
+fn submit_alias_change(
+    app: &mut AppState,
+    change: AliasChange,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if app.review_alias_changes {
+        let diff = app.alias_store.preview(&change)?;
+        if !diff.is_empty() {
+            app.show_review_dialog(PendingChange::Alias(change), diff);
+            return Ok(());
+        }
+    }
+    commit_alias_change(app, change)
+}
+
+/// Saves an alias change, or asks the user how to proceed if the rc file
+/// was edited elsewhere since it was loaded.
+fn commit_alias_change(
+    app: &mut AppState,
+    change: AliasChange,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let success = change.describe();
+    // The journal is a plain file; credentials stay out of it, at the cost
+    // of not being able to undo their changes.
+    let undo = if change.touches_secret() {
+        None
+    } else {
+        app.alias_store.inverse(&change).map(StoreChange::Alias)
+    };
+    match app.alias_store.apply(change.clone()) {
+        Ok(()) => {
+            app.mark_aliases_modified();
+            app.success_message = Some(success.clone());
+            app.record_change(success, StoreChange::Alias(change), undo);
+            app.close_dialog();
+        }
+        Err(AliasStoreError::Conflict(path)) => {
+            app.show_conflict_dialog(PendingChange::Alias(change), &path);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
 fn add_snippet(
     app: &mut AppState,
     values: &std::collections::HashMap<String, String>,
@@ -330,13 +666,17 @@ fn add_snippet(
         return Err("Title and Command are required".into());
     }
 
-    let snippet = Snippet::new(title.to_string(), command.to_string())
+    let mut snippet = Snippet::new(title.to_string(), command.to_string())
         .with_description(description.unwrap_or_default());
+    if let Some(library) = values.get(LIBRARY_FIELD) {
+        snippet.library = library.trim().to_string();
+    }
 
-    app.snippet_store.add(snippet)?;
-    app.success_message = Some(format!("Snippet '{}' added", title));
-    app.close_dialog();
-    Ok(())
+    commit_snippet_change(
+        app,
+        SnippetChange::Add(snippet),
+        format!("Snippet '{}' added", title),
+    )
 }
 
 fn edit_snippet(
@@ -344,7 +684,6 @@ fn edit_snippet(
     values: &std::collections::HashMap<String, String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(old_snippet) = app.selected_snippet() {
-        let id = old_snippet.id;
         let title = values.get("Title").map(|s| s.as_str()).unwrap_or("");
         let command = values.get("Command").map(|s| s.as_str()).unwrap_or("");
         let description = values.get("Description (optional)").and_then(|s| {
@@ -359,12 +698,17 @@ fn edit_snippet(
             return Err("Title and Command are required".into());
         }
 
-        let new_snippet = Snippet::new(title.to_string(), command.to_string())
-            .with_id(id)
-            .with_description(description.unwrap_or_default());
-
-        app.snippet_store.update(id, new_snippet)?;
-        app.success_message = Some(format!("Snippet '{}' updated", title));
+        let mut new_snippet = old_snippet.clone();
+        new_snippet.title = title.to_string();
+        new_snippet.command = command.to_string();
+        new_snippet.description = description;
+        new_snippet.updated_at = chrono::Utc::now();
+
+        return commit_snippet_change(
+            app,
+            SnippetChange::Update(new_snippet),
+            format!("Snippet '{}' updated", title),
+        );
     }
     app.close_dialog();
     Ok(())
@@ -373,14 +717,252 @@ fn edit_snippet(
 fn delete_snippet(app: &mut AppState) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(snippet) = app.selected_snippet() {
         let id = snippet.id;
-        let title = snippet.title.clone();
-        app.snippet_store.delete(&id)?;
-        app.success_message = Some(format!("Snippet '{}' deleted", title));
+        let message = format!("Snippet '{}' deleted", snippet.title);
+        return commit_snippet_change(app, SnippetChange::Delete(id), message);
+    }
+    app.close_dialog();
+    Ok(())
+}
+
+/// Moves the selected snippet to another library, or copies it there under
+/// a new id.
+fn transfer_snippet(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+    copy: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(snippet) = app.selected_snippet() {
+        let library = values
+            .get(LIBRARY_FIELD)
+            .map(|s| s.trim())
+            .unwrap_or("")
+            .to_string();
+        if library.is_empty() {
+            return Err("Library is required".into());
+        }
+
+        let mut snippet = snippet.clone();
+        snippet.library = library.clone();
+        let (change, verb) = if copy {
+            snippet.id = uuid::Uuid::new_v4();
+            (SnippetChange::Add(snippet.clone()), "copied")
+        } else {
+            (SnippetChange::Update(snippet.clone()), "moved")
+        };
+
+        return commit_snippet_change(
+            app,
+            change,
+            format!("Snippet '{}' {} to {}", snippet.title, verb, library),
+        );
     }
     app.close_dialog();
     Ok(())
 }
 
+fn commit_snippet_change(
+    app: &mut AppState,
+    change: SnippetChange,
+    success: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let undo = app.snippet_store.inverse(&change).map(StoreChange::Snippet);
+    match app.snippet_store.apply(change.clone()) {
+        Ok(()) => {
+            app.success_message = Some(success.clone());
+            app.record_change(success, StoreChange::Snippet(change), undo);
+            app.close_dialog();
+        }
+        Err(SnippetStoreError::Conflict(path)) => {
+            app.show_conflict_dialog(PendingChange::Snippet(change, success), &path);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+/// Undoes the last saved change, or redoes the last undone one. One that
+/// can no longer be applied is reported and dropped from the journal.
+fn step(app: &mut AppState, undo: bool) {
+    let operation = if undo {
+        app.journal.last_done()
+    } else {
+        app.journal.last_undone()
+    };
+    let Some(operation) = operation.cloned() else {
+        app.error_message = Some(format!("Nothing to {}", if undo { "undo" } else { "redo" }));
+        return;
+    };
+
+    let result = replay(
+        app,
+        if undo {
+            operation.undo
+        } else {
+            operation.change
+        },
+    );
+    let saved = if undo {
+        app.journal.finish_undo(result.is_ok())
+    } else {
+        app.journal.finish_redo(result.is_ok())
+    };
+    let (verb, done) = if undo {
+        ("undo", "Undone")
+    } else {
+        ("redo", "Redone")
+    };
+    match (result, saved) {
+        (Err(e), _) => {
+            app.error_message = Some(format!(
+                "Couldn't {} \"{}\": {}",
+                verb, operation.description, e
+            ))
+        }
+        (Ok(()), Err(e)) => {
+            app.error_message = Some(format!(
+                "{}: {}, but the undo history couldn't be saved: {}",
+                done, operation.description, e
+            ))
+        }
+        (Ok(()), Ok(())) => {
+            app.success_message = Some(format!("{}: {}", done, operation.description))
+        }
+    }
+}
+
+/// Applies an undo or redo, merging it into the file if that was edited
+/// elsewhere meanwhile.
+fn replay(app: &mut AppState, change: StoreChange) -> Result<(), Box<dyn std::error::Error>> {
+    match change {
+        StoreChange::Alias(change) => {
+            match app.alias_store.apply(change.clone()) {
+                Err(AliasStoreError::Conflict(_)) => app.alias_store.merge(change)?,
+                result => result?,
+            }
+            app.mark_aliases_modified();
+        }
+        StoreChange::Snippet(change) => match app.snippet_store.apply(change.clone()) {
+            Err(SnippetStoreError::Conflict(_)) => app.snippet_store.merge(change)?,
+            result => result?,
+        },
+    }
+    Ok(())
+}
+
+fn import_aliases(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let choice = values
+        .get("Adopt all, mark external or cancel? (a/x/c)")
+        .map(|s| s.trim().to_lowercase())
+        .unwrap_or_default();
+
+    let Some(PendingChange::Import(aliases)) = app.pending_change.take() else {
+        app.close_dialog();
+        return Ok(());
+    };
+
+    match choice.as_str() {
+        "a" => {
+            let (aliases, skipped) = adoptable(aliases);
+            if aliases.is_empty() {
+                app.close_dialog();
+            } else {
+                let change =
+                    AliasChange::Batch(aliases.into_iter().map(AliasChange::Add).collect());
+                submit_alias_change(app, change)?;
+            }
+            if !skipped.is_empty() {
+                app.error_message = Some(format!("Skipped {}", skipped.join("; ")));
+            }
+            return Ok(());
+        }
+        "x" => {
+            let count = aliases.len();
+            app.external_aliases.mark(aliases)?;
+            app.success_message = Some(format!("Listing {} external alias(es)", count));
+        }
+        "c" => {}
+        _ => {
+            app.pending_change = Some(PendingChange::Import(aliases));
+            return Err("Type a to adopt, x to mark external or c to cancel".into());
+        }
+    }
+
+    app.close_dialog();
+    Ok(())
+}
+
+fn confirm_review(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let confirmed = values
+        .get("Apply? (y/n)")
+        .map(|s| s.trim().eq_ignore_ascii_case("y"))
+        .unwrap_or(false);
+
+    match app.pending_change.take() {
+        Some(PendingChange::Alias(change)) if confirmed => commit_alias_change(app, change),
+        _ => {
+            app.success_message = Some("Change discarded".to_string());
+            app.close_dialog();
+            Ok(())
+        }
+    }
+}
+
+fn resolve_conflict(
+    app: &mut AppState,
+    values: &std::collections::HashMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let choice = values
+        .get("Reload, merge or abort? (r/m/a)")
+        .map(|s| s.trim().to_lowercase())
+        .unwrap_or_default();
+
+    let Some(change) = app.pending_change.take() else {
+        app.close_dialog();
+        return Ok(());
+    };
+
+    match (choice.as_str(), change) {
+        ("r", PendingChange::Alias(_)) => {
+            app.alias_store.load()?;
+            app.success_message = Some("Reloaded aliases; your change was dropped".to_string());
+        }
+        ("r", PendingChange::Snippet(..)) => {
+            app.snippet_store.load()?;
+            app.success_message = Some("Reloaded snippets; your change was dropped".to_string());
+        }
+        // Saved like any other change, so it can be undone; the conflict
+        // dialog comes back if the file changed yet again.
+        ("m", PendingChange::Alias(change)) => {
+            if let Some(change) = app.alias_store.reload_and_rebase(change)? {
+                return commit_alias_change(app, change);
+            }
+            app.success_message = Some("Reloaded aliases; nothing was left to merge".to_string());
+        }
+        ("m", PendingChange::Snippet(change, success)) => {
+            if let Some(change) = app.snippet_store.reload_and_rebase(change)? {
+                return commit_snippet_change(app, change, success);
+            }
+            app.success_message = Some("Reloaded snippets; nothing was left to merge".to_string());
+        }
+        ("a", _) => {
+            app.success_message = Some("Change aborted".to_string());
+        }
+        (_, change) => {
+            app.pending_change = Some(change);
+            return Err("Type r to reload, m to merge or a to abort".into());
+        }
+    }
+
+    app.close_dialog();
+    Ok(())
+}
+
 fn run_snippet(
     app: &mut AppState,
     values: &std::collections::HashMap<String, String>,
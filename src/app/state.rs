@@ -1,9 +1,15 @@
 #![allow(dead_code)]
-use crate::models::{Alias, Snippet};
-use crate::storage::{AliasStore, SnippetStore};
+use crate::models::{Alias, AliasSource, EnvVar, ShellFunction, Snippet};
+use crate::storage::{
+    live_aliases, load_history, suggest_variables, unmanaged_aliases, AliasChange, AliasStore,
+    ExternalAliases, HistoryEntry, Journal, Operation, Recovery, SnippetChange, SnippetStore,
+    StoreChange, GLOBAL_LIBRARY,
+};
 use crate::ui::{InputDialog, SearchBar, Tab};
-use crate::utils::UpdateInfo;
+use crate::utils::{ShadowIndex, UpdateInfo};
 use ratatui::widgets::ListState;
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Focus {
@@ -20,6 +26,22 @@ pub enum AppMode {
     Dialog,
 }
 
+/// Dialog field for zsh's alias kind; bash only has regular aliases.
+pub const ALIAS_KIND_FIELD: &str = "Kind (regular, global or suffix)";
+
+/// Dialog field naming the library a new snippet is saved to.
+pub const LIBRARY_FIELD: &str = "Library";
+
+/// A change waiting on the user: to be reviewed, to resolve a conflict
+/// with an external edit, or aliases found in the live shell to import.
+/// A snippet change keeps the message to show once it is saved.
+#[derive(Debug, Clone)]
+pub enum PendingChange {
+    Alias(AliasChange),
+    Snippet(SnippetChange, String),
+    Import(Vec<Alias>),
+}
+
 pub struct AppState {
     pub running: bool,
     pub current_tab: Tab,
@@ -27,9 +49,19 @@ pub struct AppState {
     pub mode: AppMode,
     pub search: SearchBar,
     pub alias_store: AliasStore,
+    pub external_aliases: ExternalAliases,
+    /// What alias names would shadow, for warnings in the list and dialogs.
+    pub shadow_index: ShadowIndex,
     pub snippet_store: SnippetStore,
     pub alias_list_state: ListState,
     pub snippet_list_state: ListState,
+    /// Only show snippets from this library.
+    pub library_filter: Option<String>,
+    pub function_list_state: ListState,
+    pub env_list_state: ListState,
+    /// Shell history, read the first time the History tab is opened.
+    pub history: Option<Vec<HistoryEntry>>,
+    pub history_list_state: ListState,
     pub dialog: Option<InputDialog>,
     pub help_visible: bool,
     pub error_message: Option<String>,
@@ -38,28 +70,87 @@ pub struct AppState {
     pub aliases_modified: bool,
     pub source_command: Option<String>,
     pub update_info: UpdateInfo,
+    pub pending_change: Option<PendingChange>,
+    /// Show a diff of the rc file before every alias change is written.
+    pub review_alias_changes: bool,
+    /// Stores that could not be opened at all, reported at startup.
+    pub startup_errors: Vec<String>,
+    /// A damaged library file to open in `$EDITOR` once the UI is suspended.
+    pub pending_edit: Option<Recovery>,
+    /// Saved changes, for Ctrl-z and Ctrl-r.
+    pub journal: Journal,
+    /// The live shell's aliases, being read for the import dialog.
+    live_aliases: Option<Receiver<io::Result<Vec<Alias>>>>,
 }
 
 impl AppState {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let alias_store = AliasStore::new()?;
-        let snippet_store = SnippetStore::new()?;
+    /// Never fails: a store that can't be opened is replaced by an empty
+    /// one and reported in the recovery dialog.
+    pub fn new() -> Self {
+        // Each store may fail on its own; the tabs of the other keep working.
+        let mut startup_errors = Vec::new();
+        let alias_store = AliasStore::new().unwrap_or_else(|e| {
+            startup_errors.push(format!("Aliases are unavailable: {}", e));
+            AliasStore::unavailable(e.to_string())
+        });
+        let snippet_store = SnippetStore::new().unwrap_or_else(|e| {
+            startup_errors.push(format!("Snippets are unavailable: {}", e));
+            SnippetStore::empty()
+        });
+        startup_errors.extend(snippet_store.warnings().iter().cloned());
+        for library in snippet_store.libraries() {
+            let Some(sync) = library.sync() else {
+                continue;
+            };
+            let status = sync.status();
+            if let Some(error) = &status.error {
+                startup_errors.push(format!(
+                    "Library '{}' can't be synced: {}",
+                    library.name, error
+                ));
+            } else if status.conflicted {
+                startup_errors.push(format!(
+                    "Library '{}' has unresolved git conflicts in {}. It is read-only \
+                     until you merge them with git.",
+                    library.name,
+                    sync.repo()
+                        .map_or(library.path.as_path(), |r| r.root())
+                        .display()
+                ));
+            }
+        }
+        let journal = Journal::open().unwrap_or_else(|e| {
+            startup_errors.push(format!("Undo history from the last session is lost: {}", e));
+            Journal::in_memory()
+        });
+        let external_aliases = ExternalAliases::open().unwrap_or_else(|e| {
+            startup_errors.push(format!("External aliases are unavailable: {}", e));
+            ExternalAliases::in_memory()
+        });
+        let shadow_index = ShadowIndex::scan(alias_store.config_path());
 
         let source_command = alias_store.source_command();
 
         let mut update_info = UpdateInfo::new();
         update_info.check_update();
 
-        Ok(Self {
+        let mut app = Self {
             running: true,
             current_tab: Tab::Snippets,
             focus: Focus::List,
             mode: AppMode::Normal,
             search: SearchBar::new(),
             alias_store,
+            external_aliases,
+            shadow_index,
             snippet_store,
             alias_list_state: ListState::default(),
             snippet_list_state: ListState::default(),
+            library_filter: None,
+            function_list_state: ListState::default(),
+            env_list_state: ListState::default(),
+            history: None,
+            history_list_state: ListState::default(),
             dialog: None,
             help_visible: false,
             error_message: None,
@@ -68,15 +159,31 @@ impl AppState {
             aliases_modified: false,
             source_command,
             update_info,
-        })
+            pending_change: None,
+            review_alias_changes: false,
+            startup_errors,
+            pending_edit: None,
+            journal,
+            live_aliases: None,
+        };
+        app.show_recovery_dialog();
+        app
     }
 
     pub fn next_tab(&mut self) {
         self.current_tab = self.current_tab.next();
+        self.load_history_if_needed();
     }
 
     pub fn prev_tab(&mut self) {
         self.current_tab = self.current_tab.prev();
+        self.load_history_if_needed();
+    }
+
+    fn load_history_if_needed(&mut self) {
+        if self.current_tab == Tab::History && self.history.is_none() {
+            self.history = Some(load_history());
+        }
     }
 
     pub fn focus_search(&mut self) {
@@ -92,15 +199,8 @@ impl AppState {
     }
 
     pub fn next_item(&mut self) {
-        let len = match self.current_tab {
-            Tab::Aliases => self.filtered_aliases().len(),
-            Tab::Snippets => self.filtered_snippets().len(),
-        };
-
-        let state = match self.current_tab {
-            Tab::Aliases => &mut self.alias_list_state,
-            Tab::Snippets => &mut self.snippet_list_state,
-        };
+        let len = self.current_list_len();
+        let state = self.current_list_state_mut();
 
         if len > 0 {
             let i = state
@@ -111,15 +211,8 @@ impl AppState {
     }
 
     pub fn prev_item(&mut self) {
-        let len = match self.current_tab {
-            Tab::Aliases => self.filtered_aliases().len(),
-            Tab::Snippets => self.filtered_snippets().len(),
-        };
-
-        let state = match self.current_tab {
-            Tab::Aliases => &mut self.alias_list_state,
-            Tab::Snippets => &mut self.snippet_list_state,
-        };
+        let len = self.current_list_len();
+        let state = self.current_list_state_mut();
 
         if len > 0 {
             let i = state
@@ -129,22 +222,140 @@ impl AppState {
         }
     }
 
+    fn current_list_len(&self) -> usize {
+        match self.current_tab {
+            Tab::Aliases => self.filtered_aliases().len(),
+            Tab::Snippets => self.filtered_snippets().len(),
+            Tab::Functions => self.filtered_functions().len(),
+            Tab::Env => self.filtered_env_vars().len(),
+            Tab::History => self.filtered_history().len(),
+        }
+    }
+
+    fn current_list_state_mut(&mut self) -> &mut ListState {
+        match self.current_tab {
+            Tab::Aliases => &mut self.alias_list_state,
+            Tab::Snippets => &mut self.snippet_list_state,
+            Tab::Functions => &mut self.function_list_state,
+            Tab::Env => &mut self.env_list_state,
+            Tab::History => &mut self.history_list_state,
+        }
+    }
+
+    /// Managed aliases followed by external ones that the rc file doesn't
+    /// define itself.
     pub fn filtered_aliases(&self) -> Vec<&Alias> {
-        if self.search.query.is_empty() {
+        let mut aliases = if self.search.query.is_empty() {
             self.alias_store.list()
         } else {
             self.alias_store.list_filtered(&self.search.query)
-        }
+        };
+        aliases.extend(self.external_aliases.list().into_iter().filter(|a| {
+            self.alias_store.get(&a.name).is_none() && a.matches_search(&self.search.query)
+        }));
+        aliases
     }
 
     pub fn filtered_snippets(&self) -> Vec<&Snippet> {
-        if self.search.query.is_empty() {
+        let mut snippets = if self.search.query.is_empty() {
             self.snippet_store.list()
         } else {
             self.snippet_store.list_filtered(&self.search.query)
+        };
+        if let Some(library) = &self.library_filter {
+            snippets.retain(|s| &s.library == library);
+        }
+        snippets
+    }
+
+    /// Steps the snippet list through each library in turn, then back to all.
+    pub fn cycle_library_filter(&mut self) {
+        let names: Vec<&str> = self
+            .snippet_store
+            .libraries()
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect();
+        let next = match &self.library_filter {
+            None => names.first(),
+            Some(current) => names
+                .iter()
+                .position(|n| n == current)
+                .and_then(|i| names.get(i + 1)),
+        };
+        self.library_filter = next.map(|n| n.to_string());
+        self.snippet_list_state.select(None);
+    }
+
+    pub fn filtered_functions(&self) -> Vec<&ShellFunction> {
+        if self.search.query.is_empty() {
+            self.alias_store.list_functions()
+        } else {
+            self.alias_store.list_functions_filtered(&self.search.query)
         }
     }
 
+    pub fn selected_function(&self) -> Option<&ShellFunction> {
+        let functions = self.filtered_functions();
+        self.function_list_state
+            .selected()
+            .and_then(|i| functions.get(i).copied())
+    }
+
+    pub fn filtered_env_vars(&self) -> Vec<&EnvVar> {
+        if self.search.query.is_empty() {
+            self.alias_store.list_env_vars()
+        } else {
+            self.alias_store.list_env_vars_filtered(&self.search.query)
+        }
+    }
+
+    pub fn filtered_history(&self) -> Vec<&HistoryEntry> {
+        let query = self.search.query.to_lowercase();
+        self.history
+            .iter()
+            .flatten()
+            .filter(|e| query.is_empty() || e.command.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    pub fn selected_history_entry(&self) -> Option<&HistoryEntry> {
+        let entries = self.filtered_history();
+        self.history_list_state
+            .selected()
+            .and_then(|i| entries.get(i).copied())
+    }
+
+    /// Opens the add-snippet dialog for the selected history line, with
+    /// arguments that vary across the history made into variables if
+    /// `suggest` is set.
+    pub fn show_promote_history_dialog(&mut self, suggest: bool) {
+        let Some(entry) = self.selected_history_entry() else {
+            return;
+        };
+        let suggested = suggest
+            .then(|| suggest_variables(&entry.command, self.history.as_deref().unwrap_or(&[])))
+            .flatten();
+        let message = match (suggest, &suggested) {
+            (true, Some(_)) => Some("Arguments that vary in your history are now variables"),
+            (true, None) => Some("No arguments vary between similar commands in your history"),
+            (false, _) => None,
+        };
+        let command = suggested.unwrap_or_else(|| entry.command.clone());
+
+        self.show_add_snippet_dialog_with_command(&command);
+        if let (Some(dialog), Some(message)) = (self.dialog.as_mut(), message) {
+            dialog.message = Some((message.to_string(), false));
+        }
+    }
+
+    pub fn selected_env_var(&self) -> Option<&EnvVar> {
+        let vars = self.filtered_env_vars();
+        self.env_list_state
+            .selected()
+            .and_then(|i| vars.get(i).copied())
+    }
+
     pub fn selected_alias(&self) -> Option<&Alias> {
         let aliases = self.filtered_aliases();
         self.alias_list_state
@@ -160,25 +371,82 @@ impl AppState {
     }
 
     pub fn show_add_alias_dialog(&mut self) {
+        let mut dialog = InputDialog::new("Add Alias", crate::ui::DialogMode::Add)
+            .add_field("Name")
+            .add_field("Command")
+            .add_field("Description (optional)");
+        if *self.alias_store.source() == AliasSource::Zsh {
+            dialog = dialog.add_field_with_value(ALIAS_KIND_FIELD, "regular");
+        }
+        self.dialog = Some(dialog);
+        self.focus = Focus::Dialog;
+        self.mode = AppMode::Dialog;
+    }
+
+    /// External aliases open the same form, titled for adopting them into
+    /// the rc file since they can't be edited where they are.
+    pub fn show_edit_alias_dialog(&mut self) {
+        if let Some(alias) = self.selected_alias() {
+            let title = if alias.external {
+                "Adopt Alias"
+            } else {
+                "Edit Alias"
+            };
+            let mut dialog = InputDialog::new(title, crate::ui::DialogMode::Edit)
+                .add_field_with_value("Name", &alias.name)
+                .add_field_with_value("Command", &alias.command)
+                .add_field_with_value(
+                    "Description (optional)",
+                    alias.description.as_deref().unwrap_or(""),
+                );
+            if *self.alias_store.source() == AliasSource::Zsh {
+                dialog = dialog.add_field_with_value(ALIAS_KIND_FIELD, alias.kind.as_str());
+            }
+            self.dialog = Some(dialog);
+            self.focus = Focus::Dialog;
+            self.mode = AppMode::Dialog;
+        }
+    }
+
+    pub fn show_delete_alias_dialog(&mut self) {
+        if let Some(alias) = self.selected_alias() {
+            let (title, prompt) = if alias.external {
+                (
+                    "Forget Alias",
+                    format!("Stop listing external '{}'?", alias.name),
+                )
+            } else {
+                ("Delete Alias", format!("Delete '{}'?", alias.name))
+            };
+            self.dialog = Some(
+                InputDialog::new(title, crate::ui::DialogMode::Delete)
+                    .add_field_with_value("Confirm", prompt),
+            );
+            self.focus = Focus::Dialog;
+            self.mode = AppMode::Dialog;
+        }
+    }
+
+    pub fn show_add_function_dialog(&mut self) {
         self.dialog = Some(
-            InputDialog::new("Add Alias", crate::ui::DialogMode::Add)
+            InputDialog::new("Add Function", crate::ui::DialogMode::Add)
                 .add_field("Name")
-                .add_field("Command")
+                .add_multiline_field_with_value("Body", "")
                 .add_field("Description (optional)"),
         );
         self.focus = Focus::Dialog;
         self.mode = AppMode::Dialog;
     }
 
-    pub fn show_edit_alias_dialog(&mut self) {
-        if let Some(alias) = self.selected_alias() {
+    pub fn show_edit_function_dialog(&mut self) {
+        if let Some(function) = self.selected_function() {
             self.dialog = Some(
-                InputDialog::new("Edit Alias", crate::ui::DialogMode::Edit)
-                    .add_field_with_value("Name", &alias.name)
-                    .add_field_with_value("Command", &alias.command)
+                InputDialog::new("Edit Function", crate::ui::DialogMode::Edit)
+                    .add_field_with_value("Name", &function.name)
+                    .add_multiline_field_with_value("Body", &function.body)
                     .add_field_with_value(
                         "Description (optional)",
-                        alias.description.as_deref().unwrap_or(""),
+                        function.description.as_deref().unwrap_or(""),
                     ),
             );
             self.focus = Focus::Dialog;
@@ -186,28 +454,93 @@ impl AppState {
         }
     }
 
-    pub fn show_delete_alias_dialog(&mut self) {
-        if let Some(alias) = self.selected_alias() {
+    pub fn show_delete_function_dialog(&mut self) {
+        if let Some(function) = self.selected_function() {
             self.dialog = Some(
-                InputDialog::new("Delete Alias", crate::ui::DialogMode::Delete)
-                    .add_field_with_value("Confirm", &format!("Delete '{}'?", alias.name)),
+                InputDialog::new("Delete Function", crate::ui::DialogMode::Delete)
+                    .add_field_with_value("Confirm", format!("Delete '{}'?", function.name)),
             );
             self.focus = Focus::Dialog;
             self.mode = AppMode::Dialog;
         }
     }
 
-    pub fn show_add_snippet_dialog(&mut self) {
+    pub fn show_add_env_dialog(&mut self) {
         self.dialog = Some(
-            InputDialog::new("Add Snippet", crate::ui::DialogMode::Add)
-                .add_field("Title")
-                .add_field("Command")
+            InputDialog::new("Add Variable", crate::ui::DialogMode::Add)
+                .add_field("Name")
+                .add_field("Value")
                 .add_field("Description (optional)"),
         );
         self.focus = Focus::Dialog;
         self.mode = AppMode::Dialog;
     }
 
+    pub fn show_edit_env_dialog(&mut self) {
+        if let Some(var) = self.selected_env_var() {
+            self.dialog = Some(
+                InputDialog::new("Edit Variable", crate::ui::DialogMode::Edit)
+                    .add_field_with_value("Name", &var.name)
+                    .add_field_with_value("Value", &var.value)
+                    .add_field_with_value(
+                        "Description (optional)",
+                        var.description.as_deref().unwrap_or(""),
+                    ),
+            );
+            self.focus = Focus::Dialog;
+            self.mode = AppMode::Dialog;
+        }
+    }
+
+    pub fn show_delete_env_dialog(&mut self) {
+        if let Some(var) = self.selected_env_var() {
+            self.dialog = Some(
+                InputDialog::new("Delete Variable", crate::ui::DialogMode::Delete)
+                    .add_field_with_value("Confirm", format!("Delete '{}'?", var.name)),
+            );
+            self.focus = Focus::Dialog;
+            self.mode = AppMode::Dialog;
+        }
+    }
+
+    /// Opens a list editor for a `PATH`-style variable, one field per entry
+    /// plus an empty one for adding. Clearing a field removes that entry.
+    pub fn show_path_edit_dialog(&mut self) {
+        if let Some(var) = self.selected_env_var().filter(|v| v.is_path_list()) {
+            let mut dialog = InputDialog::new(
+                format!("Edit {} entries", var.name),
+                crate::ui::DialogMode::PathEdit,
+            );
+            for (i, entry) in var.entries().iter().enumerate() {
+                dialog = dialog.add_field_with_value(format!("Entry {}", i + 1), *entry);
+            }
+            self.dialog = Some(
+                dialog
+                    .add_field("New entry")
+                    .with_message("Alt+↑/↓ to reorder, clear an entry to remove it", false),
+            );
+            self.focus = Focus::Dialog;
+            self.mode = AppMode::Dialog;
+        }
+    }
+
+    pub fn show_add_snippet_dialog(&mut self) {
+        self.show_add_snippet_dialog_with_command("");
+    }
+
+    pub fn show_add_snippet_dialog_with_command(&mut self, command: &str) {
+        let mut dialog = InputDialog::new("Add Snippet", crate::ui::DialogMode::Add)
+            .add_field("Title")
+            .add_field_with_value("Command", command)
+            .add_field("Description (optional)");
+        if self.snippet_store.libraries().len() > 1 {
+            dialog = dialog.add_field_with_value(LIBRARY_FIELD, GLOBAL_LIBRARY);
+        }
+        self.dialog = Some(dialog);
+        self.focus = Focus::Dialog;
+        self.mode = AppMode::Dialog;
+    }
+
     pub fn show_edit_snippet_dialog(&mut self) {
         if let Some(snippet) = self.selected_snippet() {
             self.dialog = Some(
@@ -228,7 +561,32 @@ impl AppState {
         if let Some(snippet) = self.selected_snippet() {
             self.dialog = Some(
                 InputDialog::new("Delete Snippet", crate::ui::DialogMode::Delete)
-                    .add_field_with_value("Confirm", &format!("Delete '{}'?", snippet.title)),
+                    .add_field_with_value("Confirm", format!("Delete '{}'?", snippet.title)),
+            );
+            self.focus = Focus::Dialog;
+            self.mode = AppMode::Dialog;
+        }
+    }
+
+    /// Asks which library to move or copy the selected snippet to.
+    pub fn show_transfer_snippet_dialog(&mut self, copy: bool) {
+        if let Some(snippet) = self.selected_snippet() {
+            let (title, mode) = if copy {
+                ("Copy Snippet", crate::ui::DialogMode::Copy)
+            } else {
+                ("Move Snippet", crate::ui::DialogMode::Move)
+            };
+            let writable: Vec<&str> = self
+                .snippet_store
+                .libraries()
+                .iter()
+                .filter(|l| !l.read_only && l.name != snippet.library)
+                .map(|l| l.name.as_str())
+                .collect();
+            self.dialog = Some(
+                InputDialog::new(format!("{} '{}'", title, snippet.title), mode)
+                    .add_field_with_value(LIBRARY_FIELD, writable.first().copied().unwrap_or(""))
+                    .with_message(format!("Libraries: {}", writable.join(", ")), false),
             );
             self.focus = Focus::Dialog;
             self.mode = AppMode::Dialog;
@@ -268,14 +626,177 @@ impl AppState {
         false
     }
 
+    pub fn show_review_dialog(&mut self, change: PendingChange, diff: String) {
+        self.pending_change = Some(change);
+        self.dialog = Some(
+            InputDialog::new("Review Changes", crate::ui::DialogMode::Review)
+                .with_preview(diff)
+                .add_field("Apply? (y/n)"),
+        );
+        self.focus = Focus::Dialog;
+        self.mode = AppMode::Dialog;
+    }
+
+    /// Lists aliases the live shell has but sniplias doesn't, to adopt into
+    /// the rc file or keep as read-only external entries. The shell is run
+    /// on another thread; the dialog fills in once it is done.
+    pub fn show_import_dialog(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        let source = self.alias_store.source().clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(live_aliases(&source));
+        });
+        self.live_aliases = Some(receiver);
+
+        self.dialog = Some(
+            InputDialog::new("Import From Live Shell", crate::ui::DialogMode::Import)
+                .with_preview("Loading aliases from your shell…"),
+        );
+        self.focus = Focus::Dialog;
+        self.mode = AppMode::Dialog;
+    }
+
+    /// Picks up work finished on other threads. Called on every pass of the
+    /// main loop.
+    pub fn poll_background(&mut self) {
+        self.snippet_store.poll_sync();
+        let Some(receiver) = &self.live_aliases else {
+            return;
+        };
+        let live = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("the shell reader stopped")),
+        };
+        self.live_aliases = None;
+        if self.dialog.as_ref().map(|d| d.mode) != Some(crate::ui::DialogMode::Import) {
+            return;
+        }
+
+        let dialog = InputDialog::new("Import From Live Shell", crate::ui::DialogMode::Import);
+        self.dialog = Some(
+            match live
+                .map(|live| unmanaged_aliases(live, &self.alias_store, &self.external_aliases))
+            {
+                Ok(aliases) if aliases.is_empty() => dialog
+                    .with_preview("Every alias in your live shell is already known to sniplias."),
+                Ok(aliases) => {
+                    let preview = aliases
+                        .iter()
+                        .map(|a| format!("{:<16} {}", a.name, a.command))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    self.pending_change = Some(PendingChange::Import(aliases));
+                    dialog
+                        .with_preview(preview)
+                        .add_field("Adopt all, mark external or cancel? (a/x/c)")
+                }
+                Err(e) => dialog.with_preview(format!("Could not read live aliases: {}", e)),
+            },
+        );
+    }
+
+    /// Reports stores that failed to open and library files that had to be
+    /// recovered, offering to open the first damaged file in `$EDITOR`.
+    pub fn show_recovery_dialog(&mut self) {
+        let recoveries = self.snippet_store.recoveries();
+        if self.startup_errors.is_empty() && recoveries.is_empty() {
+            return;
+        }
+        let report = self
+            .startup_errors
+            .iter()
+            .cloned()
+            .chain(recoveries.iter().map(Recovery::describe))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut dialog = InputDialog::new("Problems Loading Data", crate::ui::DialogMode::Recovery)
+            .with_preview(report);
+        if let Some(path) = recoveries.iter().find_map(|r| r.quarantined.as_ref()) {
+            dialog = dialog.add_field(format!(
+                "Open {} in $EDITOR to fix it? (y/n)",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+        }
+        self.dialog = Some(dialog);
+        self.focus = Focus::Dialog;
+        self.mode = AppMode::Dialog;
+    }
+
+    /// Called once the editor closes: puts the fixed file back, or shows
+    /// what is still wrong with it.
+    pub fn finish_edit(&mut self, recovery: Recovery) {
+        match self.snippet_store.reinstate(&recovery) {
+            Ok(()) => {
+                self.success_message = Some(format!("Library '{}' restored", recovery.library));
+                self.show_recovery_dialog();
+            }
+            Err(e) => {
+                self.show_recovery_dialog();
+                if let Some(dialog) = self.dialog.as_mut() {
+                    dialog.message = Some((format!("Still damaged: {}", e), true));
+                }
+            }
+        }
+    }
+
+    pub fn show_conflict_dialog(&mut self, change: PendingChange, path: &std::path::Path) {
+        self.pending_change = Some(change);
+        self.dialog = Some(
+            InputDialog::new("File Changed On Disk", crate::ui::DialogMode::Conflict)
+                .add_field("Reload, merge or abort? (r/m/a)")
+                .with_message(
+                    format!("{} was modified outside sniplias.", path.display()),
+                    true,
+                ),
+        );
+        self.focus = Focus::Dialog;
+        self.mode = AppMode::Dialog;
+    }
+
     pub fn close_dialog(&mut self) {
         self.dialog = None;
+        self.pending_change = None;
+        self.live_aliases = None;
         self.focus = Focus::List;
         self.mode = AppMode::Normal;
     }
 
+    /// Remembers a saved change so it can be undone. `undo` is None when the
+    /// change couldn't be taken back, e.g. it named something missing.
+    pub fn record_change(
+        &mut self,
+        description: String,
+        change: StoreChange,
+        undo: Option<StoreChange>,
+    ) {
+        let Some(undo) = undo else {
+            return;
+        };
+        let operation = Operation {
+            description,
+            change,
+            undo,
+        };
+        if let Err(e) = self.journal.record(operation) {
+            self.error_message = Some(format!("Failed to save the undo history: {}", e));
+        }
+    }
+
+    /// Also stops listing external aliases that the rc file now defines.
     pub fn mark_aliases_modified(&mut self) {
         self.aliases_modified = true;
+        let adopted: Vec<String> = self
+            .external_aliases
+            .list()
+            .into_iter()
+            .filter(|a| self.alias_store.get(&a.name).is_some())
+            .map(|a| a.name.clone())
+            .collect();
+        for name in adopted {
+            let _ = self.external_aliases.forget(&name);
+        }
     }
 
     pub fn quit(&mut self) {
@@ -302,6 +823,6 @@ impl AppState {
 
 impl Default for AppState {
     fn default() -> Self {
-        Self::new().expect("Failed to initialize AppState")
+        Self::new()
     }
 }
@@ -0,0 +1,790 @@
+use crate::formats::{html, markdown, navi, pet, plan_import};
+use crate::models::{Alias, AliasKind};
+use crate::storage::{
+    adoptable, atomic_write, convert_library, live_aliases, unmanaged_aliases, AliasChange,
+    AliasMode, AliasStore, Backups, Config, ExternalAliases, GitRepo, LibraryConfig, SnippetChange,
+    SnippetStore, GLOBAL_LIBRARY,
+};
+use crate::utils::ShadowIndex;
+use clap::{Subcommand, ValueEnum};
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Add, edit or remove aliases in your shell config
+    Alias {
+        #[command(subcommand)]
+        action: AliasAction,
+
+        /// Print the diff of the shell config instead of writing it
+        #[arg(long, global = true)]
+        dry_run: bool,
+    },
+    /// List, register or unregister snippet libraries
+    Library {
+        #[command(subcommand)]
+        action: Option<LibraryAction>,
+    },
+    /// Share snippet libraries through git: commit on every save, pull and push on request
+    Sync {
+        #[command(subcommand)]
+        action: Option<SyncAction>,
+    },
+    /// Import snippets from other tools; importing again updates instead of duplicating
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+
+        /// Library to add new snippets to
+        #[arg(long, global = true, default_value = GLOBAL_LIBRARY)]
+        library: String,
+
+        /// Only print what would be imported
+        #[arg(long, global = true)]
+        dry_run: bool,
+    },
+    /// Write snippets and aliases as documentation or for another tool
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// Only snippets with this tag; repeat for any of several tags
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Only snippets and aliases matching this search
+        #[arg(long)]
+        query: Option<String>,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Rewrite a snippet library in another format: .json, .toml, or a directory without extension
+    Convert {
+        from: std::path::PathBuf,
+        /// Must not exist yet
+        to: std::path::PathBuf,
+    },
+    /// List backups of your shell config, or roll back to one of them
+    Restore {
+        /// Number of the backup to restore, as shown by `sniplias restore`
+        backup: Option<usize>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SyncAction {
+    /// Show synced libraries and how they compare with their remote (the default)
+    Status,
+    /// Rebase local snippet commits onto the remote's
+    Pull {
+        /// Only this library; all synced libraries if omitted
+        library: Option<String>,
+    },
+    /// Push committed snippet changes
+    Push {
+        /// Only this library; all synced libraries if omitted
+        library: Option<String>,
+    },
+    /// Commit a library on every save; its file must be inside a git working tree
+    Enable { library: String },
+    /// Stop committing a library
+    Disable { library: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportSource {
+    /// navi cheatsheets (.cheat files, or directories of them)
+    Navi {
+        /// Defaults to navi's cheats directory
+        paths: Vec<std::path::PathBuf>,
+    },
+    /// pet's snippet.toml
+    Pet {
+        /// Defaults to ~/.config/pet/snippet.toml
+        file: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    /// Grouped by tag, with variable tables
+    Markdown,
+    /// A standalone page, laid out like the Markdown
+    Html,
+    /// A navi .cheat file (snippets only)
+    Navi,
+    /// A pet snippet.toml (snippets only)
+    Pet,
+    /// Everything sniplias knows, for scripts
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LibraryAction {
+    /// Show every library in use (the default)
+    List,
+    /// Register a snippet file, such as a team checkout or a vendor pack
+    Add {
+        name: String,
+        /// A .json or .toml file, or a directory of one file per snippet;
+        /// created on first save if missing
+        path: std::path::PathBuf,
+        /// Never write to this library
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Unregister a library; its file is left alone
+    Rm { name: String },
+    /// Move a library into a directory holding one file per snippet
+    Split { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AliasAction {
+    /// Add a new alias
+    Add {
+        name: String,
+        command: String,
+        #[arg(short, long)]
+        description: Option<String>,
+        /// Zsh alias kind: regular, global (`-g`) or suffix (`-s`)
+        #[arg(short, long, value_parser = parse_kind, default_value = "regular")]
+        kind: AliasKind,
+    },
+    /// Change an existing alias
+    Edit {
+        name: String,
+        /// New name for the alias
+        #[arg(long)]
+        rename: Option<String>,
+        #[arg(short, long)]
+        command: Option<String>,
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// Remove an alias
+    Rm { name: String },
+    /// List aliases your live shell has that sniplias doesn't manage
+    Import {
+        /// Copy these aliases into the managed block (all of them if no names are given)
+        #[arg(long, num_args = 0..)]
+        adopt: Option<Vec<String>>,
+        /// Show these aliases read-only without managing them (all if no names are given)
+        #[arg(long, num_args = 0..)]
+        external: Option<Vec<String>>,
+    },
+    /// Show or change where aliases are written: `rc` edits your shell config,
+    /// `generated` writes aliases.sh and aliases.fish for it to source
+    Mode {
+        #[arg(value_parser = parse_mode)]
+        mode: Option<AliasMode>,
+    },
+}
+
+pub fn run(command: Command, syntax_check: bool) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Alias { action, dry_run } => alias(action, dry_run, syntax_check),
+        Command::Library { action } => library(action.unwrap_or(LibraryAction::List)),
+        Command::Sync { action } => sync(action.unwrap_or(SyncAction::Status)),
+        Command::Import {
+            source,
+            library,
+            dry_run,
+        } => import_snippets(source, &library, dry_run),
+        Command::Export {
+            format,
+            tag,
+            query,
+            output,
+        } => export(format, &tag, query.as_deref(), output.as_deref()),
+        Command::Convert { from, to } => {
+            let count = convert_library(&from, &to)?;
+            println!(
+                "Wrote {} snippets from {} to {}.",
+                count,
+                from.display(),
+                to.display()
+            );
+            Ok(())
+        }
+        Command::Restore { backup } => restore(backup),
+    }
+}
+
+fn alias(
+    action: AliasAction,
+    dry_run: bool,
+    syntax_check: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let AliasAction::Mode { mode } = action {
+        return alias_mode(mode);
+    }
+    let mut store = AliasStore::new()?;
+    store.set_syntax_check(syntax_check);
+
+    let change = match action {
+        AliasAction::Add {
+            name,
+            command,
+            description,
+            kind,
+        } => {
+            let mut alias = Alias::new(name, command, store.source().clone()).with_kind(kind);
+            alias.description = description;
+            AliasChange::Add(alias)
+        }
+        AliasAction::Edit {
+            name,
+            rename,
+            command,
+            description,
+        } => {
+            let mut alias = store
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| format!("Alias not found: {}", name))?;
+            if let Some(rename) = rename {
+                alias.name = rename;
+            }
+            if let Some(command) = command {
+                alias.command = command;
+            }
+            if description.is_some() {
+                alias.description = description;
+            }
+            alias.updated_at = Some(chrono::Utc::now());
+            AliasChange::Update { name, alias }
+        }
+        AliasAction::Rm { name } => AliasChange::Delete(name),
+        AliasAction::Import { adopt, external } => {
+            return import(&mut store, adopt, external, dry_run)
+        }
+        AliasAction::Mode { .. } => unreachable!("handled above"),
+    };
+
+    let new_name = match &change {
+        AliasChange::Add(alias) => Some(&alias.name),
+        AliasChange::Update { name, alias } if *name != alias.name => Some(&alias.name),
+        _ => None,
+    };
+    if let Some(warning) =
+        new_name.and_then(|name| ShadowIndex::scan(store.config_path()).warning(name))
+    {
+        eprintln!("warning: {}", warning);
+    }
+
+    if dry_run {
+        let diff = store.preview(&change)?;
+        if diff.is_empty() {
+            println!("No changes to {}.", store.config_path().display());
+        } else {
+            print!("{}", diff);
+        }
+        return Ok(());
+    }
+
+    let summary = change.describe();
+    store.apply(change)?;
+    println!("{}.", summary);
+    if let Some(source_cmd) = store.source_command() {
+        println!("Run to reload:\n  {}", source_cmd);
+    }
+    Ok(())
+}
+
+fn alias_mode(mode: Option<AliasMode>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = Config::load()?;
+    let Some(mode) = mode else {
+        println!("{}", config.alias_mode.as_str());
+        return Ok(());
+    };
+
+    config.alias_mode = mode;
+    config.save()?;
+    let store = AliasStore::new()?;
+    println!(
+        "Aliases are now written to {}.",
+        store.config_path().display()
+    );
+    for rc in store.install_source_hooks()? {
+        println!("Added a line sourcing it to {}.", rc.display());
+    }
+    if mode == AliasMode::Generated {
+        println!(
+            "Aliases already in your shell config stay there. Run \
+             `sniplias alias import --adopt` to copy them over, then delete the originals."
+        );
+    }
+    Ok(())
+}
+
+fn import(
+    store: &mut AliasStore,
+    adopt: Option<Vec<String>>,
+    external: Option<Vec<String>>,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut externals = ExternalAliases::open()?;
+    let candidates = unmanaged_aliases(live_aliases(store.source())?, store, &externals);
+
+    if adopt.is_none() && external.is_none() {
+        if candidates.is_empty() {
+            println!("Every alias in your live shell is already known to sniplias.");
+            return Ok(());
+        }
+        println!(
+            "Aliases in your live shell but not in {}:",
+            store.config_path().display()
+        );
+        for alias in &candidates {
+            println!("  {:<16} {}", alias.name, alias.command);
+        }
+        println!(
+            "
+Run `sniplias alias import --adopt [names]` or `--external [names]`."
+        );
+        return Ok(());
+    }
+
+    let pick = |names: &[String]| -> Result<Vec<Alias>, String> {
+        if names.is_empty() {
+            return Ok(candidates.clone());
+        }
+        names
+            .iter()
+            .map(|name| {
+                candidates
+                    .iter()
+                    .find(|a| &a.name == name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        format!("'{}' is not an unmanaged alias in your live shell", name)
+                    })
+            })
+            .collect()
+    };
+
+    if let Some(names) = external {
+        let aliases = pick(&names)?;
+        if dry_run {
+            for alias in &aliases {
+                println!("Would mark '{}' as external.", alias.name);
+            }
+        } else {
+            let count = aliases.len();
+            externals.mark(aliases)?;
+            println!("Marked {} alias(es) as external.", count);
+        }
+    }
+
+    if let Some(names) = adopt {
+        let (aliases, skipped) = adoptable(pick(&names)?);
+        for reason in &skipped {
+            eprintln!("Skipping: {}", reason);
+        }
+        if aliases.is_empty() {
+            return Err("None of the aliases can be adopted".into());
+        }
+        let change = AliasChange::Batch(aliases.into_iter().map(AliasChange::Add).collect());
+        if dry_run {
+            print!("{}", store.preview(&change)?);
+        } else {
+            let summary = change.describe();
+            store.apply(change)?;
+            println!("{}.", summary);
+            if let Some(source_cmd) = store.source_command() {
+                println!("Run to reload:\n  {}", source_cmd);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn library(action: LibraryAction) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = Config::load()?;
+
+    match action {
+        LibraryAction::List => {
+            let store = SnippetStore::new()?;
+            for recovery in store.recoveries() {
+                eprintln!("{}\n", recovery.describe());
+            }
+            for warning in store.warnings() {
+                eprintln!("{}\n", warning);
+            }
+            for library in store.libraries() {
+                println!(
+                    "  {:<16} {:>4} snippets  {}{}",
+                    library.name,
+                    library.snippets().count(),
+                    library.path.display(),
+                    if library.read_only {
+                        "  (read-only)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        LibraryAction::Add {
+            name,
+            path,
+            read_only,
+        } => {
+            if name == GLOBAL_LIBRARY || config.libraries.iter().any(|l| l.name == name) {
+                return Err(format!("A library named '{}' already exists", name).into());
+            }
+            let path = std::path::absolute(&path).unwrap_or(path);
+            config.libraries.push(LibraryConfig {
+                name: name.clone(),
+                path,
+                read_only,
+            });
+            config.save()?;
+            println!("Registered library '{}'.", name);
+        }
+        LibraryAction::Rm { name } => {
+            let before = config.libraries.len();
+            config.libraries.retain(|l| l.name != name);
+            if config.libraries.len() == before {
+                return Err(format!("No registered library named '{}'", name).into());
+            }
+            config.save()?;
+            println!("Unregistered library '{}'.", name);
+        }
+        LibraryAction::Split { name } => split_library(&name, &mut config)?,
+    }
+
+    Ok(())
+}
+
+/// Writes the snippets of a library file into a directory next to it, backs
+/// the file up and removes it.
+fn split_library(name: &str, config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    let store = SnippetStore::new()?;
+    let library = store
+        .library(name)
+        .ok_or_else(|| format!("No library named '{}'", name))?;
+    if library.read_only {
+        return Err(format!("Library '{}' is read-only", name).into());
+    }
+    let from = library.path.clone();
+    let to = library.split_path().ok_or_else(|| {
+        format!(
+            "Library '{}' can't be split: {} is already a directory or is a project's .sniplias.toml",
+            name,
+            from.display()
+        )
+    })?;
+    if !from.exists() {
+        return Err(format!("{} doesn't exist yet; nothing to split", from.display()).into());
+    }
+
+    let count = convert_library(&from, &to)?;
+    let content = std::fs::read(&from)?;
+    Backups::for_library(name, &from)?.create(&content)?;
+    std::fs::remove_file(&from)?;
+
+    if let Some(entry) = config.libraries.iter_mut().find(|l| l.name == name) {
+        entry.path = to.clone();
+        config.save()?;
+    }
+    if let Some(repo) = library.sync().and_then(|s| s.repo()) {
+        repo.commit(
+            &[&from, &to],
+            &format!("Split snippet library '{}' into one file per snippet", name),
+        )?;
+    }
+    println!(
+        "Moved {} snippets from {} to {}/ (the old file is in the backups).",
+        count,
+        from.display(),
+        to.display()
+    );
+    Ok(())
+}
+
+fn sync(action: SyncAction) -> Result<(), Box<dyn std::error::Error>> {
+    let store = SnippetStore::new()?;
+    let mut config = Config::load()?;
+
+    // Libraries sharing a working tree are pulled and pushed once.
+    let repos = |only: Option<&str>| -> Result<Vec<GitRepo>, String> {
+        if let Some(name) = only {
+            if !config.synced.iter().any(|s| s == name) {
+                return Err(format!(
+                    "Library '{}' isn't synced; run `sniplias sync enable {}`",
+                    name, name
+                ));
+            }
+        }
+        let mut repos: Vec<GitRepo> = Vec::new();
+        for library in store.libraries() {
+            if only.is_some_and(|name| name != library.name) {
+                continue;
+            }
+            if let Some(repo) = library.sync().and_then(|s| s.repo()) {
+                if !repos.iter().any(|r| r.root() == repo.root()) {
+                    repos.push(repo.clone());
+                }
+            }
+        }
+        if repos.is_empty() {
+            return Err("No synced library is inside a git working tree".to_string());
+        }
+        Ok(repos)
+    };
+
+    match action {
+        SyncAction::Status => {
+            let synced: Vec<_> = store
+                .libraries()
+                .iter()
+                .filter_map(|l| Some((l, l.sync()?)))
+                .collect();
+            if synced.is_empty() {
+                println!("No synced libraries. Run `sniplias sync enable <library>`.");
+            }
+            for (library, sync) in synced {
+                let mut sync = sync.clone();
+                if let Some(repo) = sync.repo() {
+                    if let Err(e) = repo.fetch() {
+                        eprintln!("{}: {}", library.name, e);
+                    }
+                }
+                sync.refresh();
+                println!(
+                    "{:<16} {:<12} {}",
+                    library.name,
+                    sync.status().describe(),
+                    library.path.display()
+                );
+            }
+        }
+        SyncAction::Pull { library } => {
+            for repo in repos(library.as_deref())? {
+                repo.pull()?;
+                println!("Pulled {}.", repo.root().display());
+            }
+        }
+        SyncAction::Push { library } => {
+            for repo in repos(library.as_deref())? {
+                repo.push()?;
+                println!("Pushed {}.", repo.root().display());
+            }
+        }
+        SyncAction::Enable { library } => {
+            let path = &store
+                .library(&library)
+                .ok_or_else(|| format!("No library named '{}'", library))?
+                .path;
+            let repo = GitRepo::discover(path)?;
+            if !config.synced.contains(&library) {
+                config.synced.push(library.clone());
+                config.save()?;
+            }
+            if path.exists()
+                && repo.commit(&[path], &format!("Add snippet library '{}'", library))?
+            {
+                println!("Committed {}.", path.display());
+            }
+            println!(
+                "Library '{}' is now committed to {} on every save.",
+                library,
+                repo.root().display()
+            );
+        }
+        SyncAction::Disable { library } => {
+            let before = config.synced.len();
+            config.synced.retain(|name| *name != library);
+            if config.synced.len() == before {
+                return Err(format!("Library '{}' isn't synced", library).into());
+            }
+            config.save()?;
+            println!("Library '{}' is no longer committed on save.", library);
+        }
+    }
+    Ok(())
+}
+
+fn import_snippets(
+    source: ImportSource,
+    library: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tool = match &source {
+        ImportSource::Navi { .. } => "navi",
+        ImportSource::Pet { .. } => "pet",
+    };
+    let snippets = match source {
+        ImportSource::Navi { mut paths } => {
+            if paths.is_empty() {
+                paths.extend(navi::default_cheats_dir());
+            }
+            let mut snippets = Vec::new();
+            for path in paths {
+                for file in navi::cheat_files(&path)? {
+                    let content = std::fs::read_to_string(&file)
+                        .map_err(|e| format!("{}: {}", file.display(), e))?;
+                    snippets.extend(navi::parse_cheat(&content));
+                }
+            }
+            snippets
+        }
+        ImportSource::Pet { file } => {
+            let file = file
+                .or_else(pet::default_snippet_file)
+                .ok_or("Could not find pet's snippet file")?;
+            let content =
+                std::fs::read_to_string(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            let (snippets, skipped) = pet::parse_snippet_file(&content)
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            for reason in skipped {
+                eprintln!("Skipped {}", reason);
+            }
+            snippets
+        }
+    };
+
+    let mut store = SnippetStore::new()?;
+    store
+        .library(library)
+        .ok_or_else(|| format!("No library named '{}'", library))?;
+    let plan = plan_import(&store, snippets, library, tool);
+
+    if dry_run {
+        if let SnippetChange::Batch(changes) = &plan.change {
+            for change in changes {
+                match change {
+                    SnippetChange::Add(s) => println!("  add     {}", s.title),
+                    SnippetChange::Update(s) => println!("  update  {}", s.title),
+                    _ => {}
+                }
+            }
+        }
+        println!("Would import: {}.", plan.summary());
+        return Ok(());
+    }
+
+    let summary = plan.summary();
+    store.apply(plan.change)?;
+    println!("Imported: {}.", summary);
+    Ok(())
+}
+
+fn export(
+    format: ExportFormat,
+    tags: &[String],
+    query: Option<&str>,
+    output: Option<&std::path::Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let snippet_store = SnippetStore::new()?;
+    for recovery in snippet_store.recoveries() {
+        eprintln!("{}", recovery.describe());
+    }
+    // Exporting snippets should not fail on a shell config sniplias can't read.
+    let alias_store = AliasStore::new().ok();
+
+    let snippets: Vec<_> = snippet_store
+        .list()
+        .into_iter()
+        .filter(|s| tags.is_empty() || s.tags.iter().any(|t| tags.contains(t)))
+        .filter(|s| query.is_none_or(|q| s.matches_search(q)))
+        .collect();
+    // Aliases have no tags, so a tag filter leaves them out.
+    let aliases: Vec<&Alias> = match &alias_store {
+        Some(store) if tags.is_empty() => store
+            .list()
+            .into_iter()
+            .filter(|a| query.is_none_or(|q| a.matches_search(q)))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let content = match format {
+        ExportFormat::Markdown => markdown::render(&snippets, &aliases),
+        ExportFormat::Html => html::render(&snippets, &aliases),
+        ExportFormat::Navi => navi::render_cheat(&snippets),
+        ExportFormat::Pet => pet::render_snippet_file(&snippets)?,
+        ExportFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&serde_json::json!({
+                "snippets": snippets,
+                "aliases": aliases,
+            }))?;
+            json.push('\n');
+            json
+        }
+    };
+
+    match output {
+        Some(path) => {
+            atomic_write(path, content.as_bytes())?;
+            eprintln!(
+                "Exported {} snippets and {} aliases to {}.",
+                snippets.len(),
+                aliases.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn parse_mode(value: &str) -> Result<AliasMode, String> {
+    AliasMode::parse(value).ok_or_else(|| "expected rc or generated".to_string())
+}
+
+fn parse_kind(value: &str) -> Result<AliasKind, String> {
+    AliasKind::parse(value).ok_or_else(|| "expected regular, global or suffix".to_string())
+}
+
+fn restore(backup: Option<usize>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut store = AliasStore::new()?;
+    let backups = store.backups().list()?;
+    let config_path = store.config_path().display().to_string();
+
+    let Some(number) = backup else {
+        if backups.is_empty() {
+            println!("No backups of {} yet.", config_path);
+            return Ok(());
+        }
+        println!("Backups of {} (newest first):", config_path);
+        for (i, entry) in backups.iter().enumerate() {
+            println!(
+                "  {:>2}  {}",
+                i + 1,
+                entry
+                    .created_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        println!(
+            "\nStored in {}. Run `sniplias restore <number>` to roll back.",
+            store.backups().dir().display()
+        );
+        return Ok(());
+    };
+
+    let entry = number
+        .checked_sub(1)
+        .and_then(|i| backups.get(i))
+        .ok_or_else(|| format!("No backup number {}", number))?;
+
+    store.restore(entry)?;
+    println!(
+        "Restored {} from the backup taken {}.",
+        config_path,
+        entry
+            .created_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+    if let Some(source_cmd) = store.source_command() {
+        println!("Run to reload:\n  {}", source_cmd);
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! A standalone HTML page, for wikis that take HTML rather than Markdown.
+
+use super::group_by_tag;
+use crate::models::{Alias, Snippet};
+
+pub fn render(snippets: &[&Snippet], aliases: &[&Alias]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Snippets</title>\n</head>\n<body>\n",
+    );
+
+    if !snippets.is_empty() {
+        out.push_str("<h1>Snippets</h1>\n");
+        for (group, snippets) in group_by_tag(snippets) {
+            out.push_str(&format!("<h2>{}</h2>\n", escape(&group)));
+            for snippet in snippets {
+                render_snippet(&mut out, snippet);
+            }
+        }
+    }
+
+    if !aliases.is_empty() {
+        out.push_str("<h1>Aliases</h1>\n<table>\n<tr><th>Alias</th><th>Command</th><th>Description</th></tr>\n");
+        for alias in aliases {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td><code>{}</code></td><td>{}</td></tr>\n",
+                escape(&alias.name),
+                escape(&alias.command),
+                escape(alias.description.as_deref().unwrap_or(""))
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_snippet(out: &mut String, snippet: &Snippet) {
+    out.push_str(&format!("<h3>{}</h3>\n", escape(&snippet.title)));
+    if let Some(description) = snippet.description.as_deref().filter(|d| !d.is_empty()) {
+        out.push_str(&format!("<p>{}</p>\n", escape(description)));
+    }
+    if !snippet.tags.is_empty() {
+        let tags: Vec<String> = snippet
+            .tags
+            .iter()
+            .map(|t| format!("<code>{}</code>", escape(t)))
+            .collect();
+        out.push_str(&format!("<p>Tags: {}</p>\n", tags.join(", ")));
+    }
+    out.push_str(&format!(
+        "<pre><code class=\"language-sh\">{}</code></pre>\n",
+        escape(&snippet.command)
+    ));
+
+    let variables = snippet.extract_variables();
+    if !variables.is_empty() {
+        out.push_str("<table>\n<tr><th>Variable</th><th>Default</th><th>Choices from</th></tr>\n");
+        for variable in variables {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+                escape(&variable.name),
+                escape(variable.default_value.as_deref().unwrap_or("")),
+                escape(
+                    snippet
+                        .generators
+                        .get(&variable.name)
+                        .map_or("", String::as_str)
+                )
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,116 @@
+//! Runbook-style Markdown, for wikis and READMEs.
+
+use super::group_by_tag;
+use crate::models::{Alias, Snippet};
+
+pub fn render(snippets: &[&Snippet], aliases: &[&Alias]) -> String {
+    let mut out = String::new();
+
+    if !snippets.is_empty() {
+        out.push_str("# Snippets\n");
+        for (group, snippets) in group_by_tag(snippets) {
+            out.push_str(&format!("\n## {}\n", group));
+            for snippet in snippets {
+                render_snippet(&mut out, snippet);
+            }
+        }
+    }
+
+    if !aliases.is_empty() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str("# Aliases\n\n| Alias | Command | Description |\n|---|---|---|\n");
+        for alias in aliases {
+            out.push_str(&format!(
+                "| `{}` | `{}` | {} |\n",
+                cell(&alias.name),
+                cell(&alias.command),
+                cell(alias.description.as_deref().unwrap_or(""))
+            ));
+        }
+    }
+
+    out
+}
+
+fn render_snippet(out: &mut String, snippet: &Snippet) {
+    out.push_str(&format!("\n### {}\n\n", snippet.title));
+    if let Some(description) = snippet.description.as_deref().filter(|d| !d.is_empty()) {
+        out.push_str(&format!("{}\n\n", description));
+    }
+    if !snippet.tags.is_empty() {
+        let tags: Vec<String> = snippet.tags.iter().map(|t| format!("`{}`", t)).collect();
+        out.push_str(&format!("Tags: {}\n\n", tags.join(", ")));
+    }
+
+    // A fence longer than any run of backticks in the command.
+    let longest = snippet
+        .command
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    out.push_str(&format!("{}sh\n{}\n{}\n", fence, snippet.command, fence));
+
+    let variables = snippet.extract_variables();
+    if !variables.is_empty() {
+        let with_generators = !snippet.generators.is_empty();
+        out.push_str("\n| Variable | Default |");
+        out.push_str(if with_generators {
+            " Choices from |\n|---|---|---|\n"
+        } else {
+            "\n|---|---|\n"
+        });
+        for variable in variables {
+            out.push_str(&format!(
+                "| `{}` | {} |",
+                variable.name,
+                variable
+                    .default_value
+                    .map(|d| format!("`{}`", cell(&d)))
+                    .unwrap_or_default()
+            ));
+            if with_generators {
+                let generator = snippet
+                    .generators
+                    .get(&variable.name)
+                    .map(|g| format!("`{}`", cell(g)))
+                    .unwrap_or_default();
+                out.push_str(&format!(" {} |", generator));
+            }
+            out.push('\n');
+        }
+    }
+}
+
+/// Keeps a value on one table row.
+fn cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_groups_by_tag_with_variable_tables() {
+        let mut deploy = Snippet::new("Deploy".into(), "make deploy ENV={{env:staging}}".into());
+        deploy.tags = vec!["ops".into()];
+        let plain = Snippet::new("List".into(), "ls -la".into());
+        let alias = Alias::new(
+            "gs".into(),
+            "git status".into(),
+            crate::models::AliasSource::Bash,
+        );
+
+        let out = render(&[&plain, &deploy], &[&alias]);
+        let ops = out.find("## ops").unwrap();
+        let other = out.find("## Other").unwrap();
+        assert!(ops < other);
+        assert!(out.contains("```sh\nmake deploy ENV={{env:staging}}\n```"));
+        assert!(out.contains("| `env` | `staging` |"));
+        assert!(out.contains("| `gs` | `git status` |  |"));
+    }
+}
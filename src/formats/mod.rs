@@ -0,0 +1,161 @@
+//! Formats of other tools and documents, to import snippets from and
+//! export them to.
+
+pub mod html;
+pub mod markdown;
+pub mod navi;
+pub mod pet;
+
+use crate::models::Snippet;
+use crate::storage::{SnippetChange, SnippetStore};
+use std::collections::BTreeMap;
+
+/// What importing a set of snippets would do.
+pub struct ImportPlan {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Entries repeating a command seen earlier in the same import.
+    pub duplicates: usize,
+    pub change: SnippetChange,
+}
+
+impl ImportPlan {
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} added, {} updated, {} unchanged",
+            self.added, self.updated, self.unchanged
+        );
+        if self.duplicates > 0 {
+            summary.push_str(&format!(", {} duplicates skipped", self.duplicates));
+        }
+        summary
+    }
+}
+
+/// Matches `incoming` against the store by command, so importing the same
+/// file again updates titles, tags and generators instead of duplicating.
+/// Only snippets that came from an earlier import are updated; one written
+/// by hand is left as it is. New snippets go to `library`, marked as
+/// imported from `tool`.
+pub fn plan_import(
+    store: &SnippetStore,
+    incoming: Vec<Snippet>,
+    library: &str,
+    tool: &str,
+) -> ImportPlan {
+    let (mut added, mut updated, mut unchanged, mut duplicates) = (0, 0, 0, 0);
+    let mut changes = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+
+    for mut snippet in incoming {
+        if !seen.insert(snippet.command.clone()) {
+            duplicates += 1;
+            continue;
+        }
+        match store
+            .list()
+            .into_iter()
+            .find(|s| s.command == snippet.command)
+        {
+            // Read-only libraries are left as they are, even if the import differs.
+            Some(existing)
+                if existing.imported_from.is_none()
+                    || (existing.title == snippet.title
+                        && existing.tags == snippet.tags
+                        && existing.generators == snippet.generators)
+                    || store
+                        .library(&existing.library)
+                        .is_some_and(|l| l.read_only) =>
+            {
+                unchanged += 1;
+            }
+            Some(existing) => {
+                let mut merged = existing.clone();
+                merged.title = snippet.title;
+                merged.description = snippet.description.or(merged.description);
+                merged.tags = snippet.tags;
+                merged.generators = snippet.generators;
+                merged.updated_at = chrono::Utc::now();
+                changes.push(SnippetChange::Update(merged));
+                updated += 1;
+            }
+            None => {
+                snippet.library = library.to_string();
+                snippet.imported_from = Some(tool.to_string());
+                changes.push(SnippetChange::Add(snippet));
+                added += 1;
+            }
+        }
+    }
+
+    ImportPlan {
+        added,
+        updated,
+        unchanged,
+        duplicates,
+        change: SnippetChange::Batch(changes),
+    }
+}
+
+/// Snippets under their first tag, with tags in alphabetical order and
+/// untagged snippets last under "Other". Sorted by title within each group.
+pub fn group_by_tag<'a>(snippets: &[&'a Snippet]) -> Vec<(String, Vec<&'a Snippet>)> {
+    let mut tagged: BTreeMap<String, Vec<&Snippet>> = BTreeMap::new();
+    let mut other = Vec::new();
+    for snippet in snippets {
+        match snippet.tags.first() {
+            Some(tag) => tagged.entry(tag.clone()).or_default().push(*snippet),
+            None => other.push(*snippet),
+        }
+    }
+
+    let mut groups: Vec<(String, Vec<&Snippet>)> = tagged.into_iter().collect();
+    if !other.is_empty() {
+        groups.push(("Other".to_string(), other));
+    }
+    for (_, snippets) in &mut groups {
+        snippets.sort_by(|a, b| a.title.cmp(&b.title));
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Library, Scope, GLOBAL_LIBRARY};
+
+    #[test]
+    fn test_reimport_updates_only_imported_snippets() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::open(
+            GLOBAL_LIBRARY.into(),
+            dir.path().join("snippets.json"),
+            Scope::Global,
+        )
+        .unwrap();
+        let mut store = SnippetStore::with_libraries(vec![library]);
+        store
+            .add(Snippet::new("My logs".into(), "kubectl logs -f".into()))
+            .unwrap();
+
+        let incoming = || {
+            vec![
+                Snippet::new("Follow logs".into(), "kubectl logs -f".into()),
+                Snippet::new("Pods".into(), "kubectl get pods".into()),
+            ]
+        };
+        let plan = plan_import(&store, incoming(), GLOBAL_LIBRARY, "navi");
+        assert_eq!((plan.added, plan.updated, plan.unchanged), (1, 0, 1));
+        store.apply(plan.change).unwrap();
+
+        let mut renamed = incoming();
+        renamed[1].title = "List pods".into();
+        let plan = plan_import(&store, renamed, GLOBAL_LIBRARY, "navi");
+        assert_eq!((plan.added, plan.updated, plan.unchanged), (0, 1, 1));
+        store.apply(plan.change).unwrap();
+
+        let titles: Vec<_> = store.list().iter().map(|s| s.title.clone()).collect();
+        assert_eq!(titles, ["My logs", "List pods"]);
+    }
+}
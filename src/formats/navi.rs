@@ -0,0 +1,233 @@
+//! navi cheatsheets: `% tags`, `# description`, command lines and
+//! `$ var: generator` lines, with `<var>` placeholders.
+
+use crate::models::Snippet;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where navi keeps downloaded and personal cheatsheets.
+pub fn default_cheats_dir() -> Option<PathBuf> {
+    directories::BaseDirs::new().map(|d| d.data_dir().join("navi").join("cheats"))
+}
+
+/// `.cheat` files at `path`, searching directories recursively.
+pub fn cheat_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut found = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            found.extend(cheat_files(&path)?);
+        } else if path.extension().is_some_and(|e| e == "cheat") {
+            found.push(path);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// A `%` section: its tags, generators and the entries under it.
+#[derive(Default)]
+struct Section {
+    tags: Vec<String>,
+    generators: BTreeMap<String, String>,
+    entries: Vec<(Option<String>, Vec<String>)>,
+}
+
+/// Snippets from the contents of one `.cheat` file. Generators apply to the
+/// whole section they appear in, so each snippet keeps those it uses.
+pub fn parse_cheat(content: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut section = Section::default();
+    let mut description: Option<String> = None;
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let marker = trimmed.chars().next();
+
+        if matches!(marker, None | Some('%' | '#' | '$' | ';' | '@')) && !lines.is_empty() {
+            section
+                .entries
+                .push((description.take(), std::mem::take(&mut lines)));
+        }
+
+        match marker {
+            None | Some(';' | '@') => {}
+            Some('%') => {
+                snippets.extend(finish(std::mem::take(&mut section)));
+                description = None;
+                section.tags = trimmed[1..]
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+            }
+            Some('#') => description = Some(trimmed[1..].trim().to_string()),
+            Some('$') => {
+                if let Some((name, generator)) = trimmed[1..].split_once(':') {
+                    section
+                        .generators
+                        .insert(variable_name(name.trim()), generator.trim().to_string());
+                }
+            }
+            Some(_) => lines.push(line.trim_end().to_string()),
+        }
+    }
+
+    if !lines.is_empty() {
+        section.entries.push((description, lines));
+    }
+    snippets.extend(finish(section));
+    snippets
+}
+
+fn finish(section: Section) -> Vec<Snippet> {
+    let placeholder = Regex::new(r"<([A-Za-z_][\w-]*)>").unwrap();
+
+    section
+        .entries
+        .into_iter()
+        .map(|(description, lines)| {
+            let command = placeholder
+                .replace_all(&lines.join("\n"), |caps: &regex::Captures| {
+                    format!("{{{{{}}}}}", variable_name(&caps[1]))
+                })
+                .into_owned();
+            let title = description
+                .filter(|d| !d.is_empty())
+                .unwrap_or_else(|| lines[0].trim().to_string());
+
+            let mut snippet = Snippet::new(title, command);
+            snippet.tags = section.tags.clone();
+            snippet.generators = snippet
+                .extract_variables()
+                .into_iter()
+                .filter_map(|v| {
+                    let generator = section.generators.get(&v.name)?;
+                    Some((v.name, generator.clone()))
+                })
+                .collect();
+            snippet
+        })
+        .collect()
+}
+
+/// A `.cheat` file with a `%` section per set of tags. Variables become
+/// `<var>`; generators are written back, and defaults without one become
+/// `$ var: echo '<default>'` so navi still offers them.
+pub fn render_cheat(snippets: &[&Snippet]) -> String {
+    let placeholder = Regex::new(r"\{\{(\w+)(?::[^}]*)?\}\}").unwrap();
+    // Sections in the order their tags first appear.
+    let mut sections: Vec<(String, Vec<&Snippet>)> = Vec::new();
+    for snippet in snippets {
+        let tags = if snippet.tags.is_empty() {
+            "sniplias".to_string()
+        } else {
+            snippet.tags.join(", ")
+        };
+        match sections.iter_mut().find(|(t, _)| *t == tags) {
+            Some((_, section)) => section.push(snippet),
+            None => sections.push((tags, vec![snippet])),
+        }
+    }
+
+    let mut out = String::new();
+    for (tags, snippets) in sections {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("% {}\n", tags));
+        for snippet in snippets {
+            out.push_str(&format!(
+                "\n# {}\n{}\n",
+                snippet.title,
+                placeholder.replace_all(&snippet.command, "<$1>")
+            ));
+            let mut lines = Vec::new();
+            for variable in snippet.extract_variables() {
+                if let Some(generator) = snippet.generators.get(&variable.name) {
+                    lines.push(format!("$ {}: {}", variable.name, generator));
+                } else if let Some(default) = variable.default_value {
+                    lines.push(format!(
+                        "$ {}: echo '{}'",
+                        variable.name,
+                        default.replace('\'', "'\\''")
+                    ));
+                }
+            }
+            if !lines.is_empty() {
+                out.push_str(&format!("\n{}\n", lines.join("\n")));
+            }
+        }
+    }
+    out
+}
+
+/// sniplias variables are `\w+`; navi also allows dashes.
+fn variable_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cheat() {
+        let cheat = "\
+% git, code
+
+# Change branch
+git checkout <branch-name>
+
+; not a snippet
+# Show a file at a revision
+git show <rev>:<file> \\
+  | less
+
+$ branch-name: git branch | awk '{print $NF}'
+$ rev: git log --format=%h --- --column 1
+
+% docker
+docker ps
+";
+        let snippets = parse_cheat(cheat);
+        assert_eq!(snippets.len(), 3);
+
+        assert_eq!(snippets[0].title, "Change branch");
+        assert_eq!(snippets[0].command, "git checkout {{branch_name}}");
+        assert_eq!(snippets[0].tags, vec!["git", "code"]);
+        assert_eq!(
+            snippets[0].generators["branch_name"],
+            "git branch | awk '{print $NF}'"
+        );
+
+        assert_eq!(
+            snippets[1].command,
+            "git show {{rev}}:{{file}} \\\n  | less"
+        );
+        assert_eq!(
+            snippets[1].generators.keys().collect::<Vec<_>>(),
+            vec!["rev"]
+        );
+
+        assert_eq!(snippets[2].title, "docker ps");
+        assert_eq!(snippets[2].tags, vec!["docker"]);
+
+        let exported = render_cheat(&snippets.iter().collect::<Vec<_>>());
+        let reimported = parse_cheat(&exported);
+        assert_eq!(reimported.len(), 3);
+        for (a, b) in snippets.iter().zip(&reimported) {
+            assert_eq!(
+                (&a.title, &a.command, &a.tags, &a.generators),
+                (&b.title, &b.command, &b.tags, &b.generators)
+            );
+        }
+    }
+}
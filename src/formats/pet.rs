@@ -0,0 +1,157 @@
+//! pet's `snippet.toml`: `description`, `command` and `tag` per snippet, with
+//! `<param>` and `<param=default>` placeholders.
+
+use crate::models::Snippet;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize)]
+struct PetFile {
+    #[serde(default)]
+    snippets: Vec<PetSnippet>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PetSnippet {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    command: String,
+    #[serde(default)]
+    tag: Vec<String>,
+    #[serde(default)]
+    output: String,
+}
+
+/// Where pet keeps its snippets unless configured otherwise.
+pub fn default_snippet_file() -> Option<PathBuf> {
+    directories::BaseDirs::new().map(|d| d.home_dir().join(".config/pet/snippet.toml"))
+}
+
+/// Snippets from a pet `snippet.toml`, and a line for each entry that could
+/// not be converted.
+pub fn parse_snippet_file(content: &str) -> Result<(Vec<Snippet>, Vec<String>), toml::de::Error> {
+    let file: PetFile = toml::from_str(content)?;
+    let placeholder = Regex::new(r"<([^<>=\s]+)(?:=([^<>]*))?>").unwrap();
+
+    let mut snippets = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, pet) in file.snippets.into_iter().enumerate() {
+        let label = if pet.description.is_empty() {
+            format!("#{}", i + 1)
+        } else {
+            format!("'{}'", pet.description)
+        };
+        if pet.command.trim().is_empty() {
+            skipped.push(format!("{}: no command", label));
+            continue;
+        }
+
+        let mut problem = None;
+        let command = placeholder.replace_all(&pet.command, |caps: &regex::Captures| {
+            let name = caps[1].replace('-', "_");
+            if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                problem = Some(format!(
+                    "parameter <{}> is not a valid variable name",
+                    &caps[1]
+                ));
+            }
+            match caps.get(2).map(|d| first_choice(d.as_str())) {
+                Some(default) if default.contains('}') => {
+                    problem = Some(format!("default of <{}> contains '}}'", &caps[1]));
+                    String::new()
+                }
+                Some(default) => format!("{{{{{}:{}}}}}", name, default),
+                None => format!("{{{{{}}}}}", name),
+            }
+        });
+        if let Some(problem) = problem {
+            skipped.push(format!("{}: {}", label, problem));
+            continue;
+        }
+
+        let title = if pet.description.is_empty() {
+            pet.command
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        } else {
+            pet.description
+        };
+        let mut snippet = Snippet::new(title, command.into_owned());
+        snippet.tags = pet.tag;
+        snippets.push(snippet);
+    }
+
+    Ok((snippets, skipped))
+}
+
+/// A pet `snippet.toml`, titles as descriptions and variables as
+/// `<param>` or `<param=default>`.
+pub fn render_snippet_file(snippets: &[&Snippet]) -> Result<String, toml::ser::Error> {
+    let placeholder = Regex::new(r"\{\{(\w+)(?::([^}]*))?\}\}").unwrap();
+    let file = PetFile {
+        snippets: snippets
+            .iter()
+            .map(|snippet| PetSnippet {
+                description: snippet.title.clone(),
+                command: placeholder
+                    .replace_all(&snippet.command, |caps: &regex::Captures| {
+                        match caps.get(2) {
+                            Some(default) => format!("<{}={}>", &caps[1], default.as_str()),
+                            None => format!("<{}>", &caps[1]),
+                        }
+                    })
+                    .into_owned(),
+                tag: snippet.tags.clone(),
+                output: String::new(),
+            })
+            .collect(),
+    };
+    toml::to_string_pretty(&file)
+}
+
+/// pet offers a list of choices as `|_one_||_two_|`; the first becomes the
+/// default.
+fn first_choice(default: &str) -> &str {
+    default
+        .strip_prefix("|_")
+        .and_then(|rest| rest.split("_|").next())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snippet_file() {
+        let content = r#"
+[[snippets]]
+  description = "Grep logs"
+  command = "grep <pattern> <file=/var/log/syslog> | tail -n <lines=|_20_||_100_|>"
+  tag = ["logs"]
+  output = ""
+
+[[snippets]]
+  description = "Empty"
+  command = ""
+
+[[snippets]]
+  description = "Braces"
+  command = "echo <json={}>"
+"#;
+        let (snippets, skipped) = parse_snippet_file(content).unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(
+            snippets[0].command,
+            "grep {{pattern}} {{file:/var/log/syslog}} | tail -n {{lines:20}}"
+        );
+        assert_eq!(snippets[0].tags, vec!["logs"]);
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[1].contains("'Braces'"));
+    }
+}
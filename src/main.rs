@@ -1,4 +1,6 @@
 mod app;
+mod cli;
+mod formats;
 mod models;
 mod storage;
 mod ui;
@@ -17,13 +19,30 @@ use utils::Terminal;
 struct Args {
     #[arg(short, long, default_value = "false")]
     debug: bool,
+
+    /// Review a diff of your shell config before each alias change is saved
+    #[arg(short, long)]
+    review: bool,
+
+    /// Don't run `bash -n`/`zsh -n` over generated lines before saving
+    #[arg(long, global = true)]
+    no_syntax_check: bool,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _args = Args::parse();
+    let args = Args::parse();
+
+    if let Some(command) = args.command {
+        return cli::run(command, !args.no_syntax_check);
+    }
 
     let mut terminal = Terminal::new()?;
-    let mut app = AppState::new()?;
+    let mut app = AppState::new();
+    app.review_alias_changes = args.review;
+    app.alias_store.set_syntax_check(!args.no_syntax_check);
     let events = EventHandler::default();
     let theme = Theme::default();
 
@@ -40,6 +59,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             app::Event::Tick => {}
         }
+        app.poll_background();
+
+        if let Some(recovery) = app.pending_edit.take() {
+            // Hand the terminal to the editor, then take it back.
+            drop(terminal);
+            let path = recovery.quarantined.clone().unwrap_or_default();
+            let edited = utils::open_in_editor(&path);
+            terminal = Terminal::new()?;
+            match edited {
+                Ok(_) => app.finish_edit(recovery),
+                Err(e) => {
+                    app.show_recovery_dialog();
+                    if let Some(dialog) = app.dialog.as_mut() {
+                        dialog.message = Some((format!("Could not start editor: {}", e), true));
+                    }
+                }
+            }
+        }
     }
 
     drop(terminal);
@@ -64,9 +101,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Show update available message on exit
     if app.update_info.update_available {
-        eprintln!("\nUpdate available: {} -> {}",
+        eprintln!(
+            "\nUpdate available: {} -> {}",
             app.update_info.local_version,
-            app.update_info.remote_version.as_deref().unwrap_or("unknown")
+            app.update_info
+                .remote_version
+                .as_deref()
+                .unwrap_or("unknown")
         );
         eprintln!("Press 'u' in the app to update.\n");
     }
@@ -93,28 +134,60 @@ fn render_ui(f: &mut ratatui::Frame, app: &AppState, theme: &Theme, area: ratatu
 
     match app.current_tab {
         ui::Tab::Aliases => {
-            let aliases = app.filtered_aliases();
+            let rows: Vec<AliasRow> = app
+                .filtered_aliases()
+                .into_iter()
+                .map(|alias| AliasRow {
+                    alias,
+                    warning: app.shadow_index.warning(&alias.name),
+                })
+                .collect();
+            let rows: Vec<&AliasRow> = rows.iter().collect();
             let mut state = app.alias_list_state.clone();
-            render_list(
-                f,
-                chunks[2],
-                &aliases.iter().map(|a| *a).collect::<Vec<_>>(),
-                &mut state,
-                theme,
-                "ALIASES",
-            );
+            render_list(f, chunks[2], &rows, &mut state, theme, "ALIASES");
+        }
+        ui::Tab::Functions => {
+            let functions = app.filtered_functions();
+            let mut state = app.function_list_state.clone();
+            render_list(f, chunks[2], &functions, &mut state, theme, "FUNCTIONS");
+        }
+        ui::Tab::Env => {
+            let vars = app.filtered_env_vars();
+            let mut state = app.env_list_state.clone();
+            render_list(f, chunks[2], &vars, &mut state, theme, "ENV");
+        }
+        ui::Tab::History => {
+            let rows: Vec<HistoryRow> = app
+                .filtered_history()
+                .into_iter()
+                .map(|entry| HistoryRow {
+                    command: entry.command.lines().next().unwrap_or_default(),
+                    count: format!("×{}", entry.count),
+                })
+                .collect();
+            let rows: Vec<&HistoryRow> = rows.iter().collect();
+            let mut state = app.history_list_state.clone();
+            render_list(f, chunks[2], &rows, &mut state, theme, "HISTORY");
         }
         ui::Tab::Snippets => {
-            let snippets = app.filtered_snippets();
+            let rows: Vec<SnippetRow> = app
+                .filtered_snippets()
+                .into_iter()
+                .map(|snippet| SnippetRow {
+                    snippet,
+                    scope: app
+                        .snippet_store
+                        .library(&snippet.library)
+                        .and_then(library_badge),
+                })
+                .collect();
+            let rows: Vec<&SnippetRow> = rows.iter().collect();
+            let title = match &app.library_filter {
+                Some(library) => format!("SNIPPETS · {}", library),
+                None => "SNIPPETS".to_string(),
+            };
             let mut state = app.snippet_list_state.clone();
-            render_list(
-                f,
-                chunks[2],
-                &snippets.iter().map(|s| *s).collect::<Vec<_>>(),
-                &mut state,
-                theme,
-                "SNIPPETS",
-            );
+            render_list(f, chunks[2], &rows, &mut state, theme, &title);
         }
     }
 
@@ -125,8 +198,14 @@ fn render_ui(f: &mut ratatui::Frame, app: &AppState, theme: &Theme, area: ratatu
         app.current_tab,
         app.search.focused,
         app.dialog.is_some(),
+        app.dialog.as_ref().is_some_and(|d| d.in_multiline_field()),
         app.help_visible,
         &app.update_info,
+        sync_indicator(&app.snippet_store),
+        app.error_message
+            .as_deref()
+            .map(|m| (m, true))
+            .or(app.success_message.as_deref().map(|m| (m, false))),
     );
 
     if app.help_visible {
@@ -146,6 +225,40 @@ impl ui::Listable for models::Alias {
     fn subtitle(&self) -> Option<&str> {
         Some(&self.command)
     }
+
+    fn badge(&self) -> Option<&str> {
+        if self.external {
+            return Some("external");
+        }
+        match self.kind {
+            models::AliasKind::Regular => None,
+            kind => Some(kind.as_str()),
+        }
+    }
+}
+
+/// An alias with what its name shadows, if anything.
+struct AliasRow<'a> {
+    alias: &'a models::Alias,
+    warning: Option<String>,
+}
+
+impl ui::Listable for AliasRow<'_> {
+    fn title(&self) -> &str {
+        self.alias.title()
+    }
+
+    fn subtitle(&self) -> Option<&str> {
+        self.alias.subtitle()
+    }
+
+    fn badge(&self) -> Option<&str> {
+        self.alias.badge()
+    }
+
+    fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
 }
 
 impl ui::Listable for models::Snippet {
@@ -157,3 +270,107 @@ impl ui::Listable for models::Snippet {
         self.description.as_deref()
     }
 }
+
+/// "sync: team ↑2" for the synced libraries, and whether any of them needs
+/// attention. Errors are left to the CLI; the bar only says something is off.
+fn sync_indicator(store: &storage::SnippetStore) -> Option<(String, bool)> {
+    let synced: Vec<_> = store
+        .libraries()
+        .iter()
+        .filter_map(|l| Some((&l.name, l.sync()?.status())))
+        .collect();
+    if synced.is_empty() {
+        return None;
+    }
+    let problem = synced.iter().any(|(_, status)| status.is_problem());
+    let text = synced
+        .iter()
+        .map(|(name, status)| {
+            let state = if status.error.is_some() {
+                "error".to_string()
+            } else {
+                status.describe()
+            };
+            format!("{} {}", name, state)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some((format!("sync: {}", text), problem))
+}
+
+/// Marks snippets outside the global library with where they live.
+fn library_badge(library: &storage::Library) -> Option<String> {
+    let badge = match library.scope {
+        storage::Scope::Global => return None,
+        storage::Scope::Project => format!("project: {}", library.name),
+        storage::Scope::Registered => library.name.clone(),
+    };
+    Some(if library.read_only {
+        format!("{}, read-only", badge)
+    } else {
+        badge
+    })
+}
+
+/// A snippet with a marker for the library it comes from.
+struct SnippetRow<'a> {
+    snippet: &'a models::Snippet,
+    scope: Option<String>,
+}
+
+impl ui::Listable for SnippetRow<'_> {
+    fn title(&self) -> &str {
+        self.snippet.title()
+    }
+
+    fn subtitle(&self) -> Option<&str> {
+        self.snippet.subtitle()
+    }
+
+    fn badge(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+}
+
+/// A history line with how often it was run.
+struct HistoryRow<'a> {
+    command: &'a str,
+    count: String,
+}
+
+impl ui::Listable for HistoryRow<'_> {
+    fn title(&self) -> &str {
+        self.command
+    }
+
+    fn subtitle(&self) -> Option<&str> {
+        None
+    }
+
+    fn badge(&self) -> Option<&str> {
+        Some(&self.count)
+    }
+}
+
+impl ui::Listable for models::ShellFunction {
+    fn title(&self) -> &str {
+        &self.name
+    }
+
+    fn subtitle(&self) -> Option<&str> {
+        self.body
+            .lines()
+            .find(|l| !l.trim().is_empty())
+            .map(str::trim)
+    }
+}
+
+impl ui::Listable for models::EnvVar {
+    fn title(&self) -> &str {
+        &self.name
+    }
+
+    fn subtitle(&self) -> Option<&str> {
+        Some(self.display_value())
+    }
+}
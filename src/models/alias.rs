@@ -1,4 +1,6 @@
 #![allow(dead_code)]
+use super::metadata::Metadata;
+use super::shell;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -9,9 +11,67 @@ pub struct Alias {
     pub name: String,
     pub command: String,
     pub description: Option<String>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+    /// None for a line sniplias hasn't stamped yet.
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// The user's own trailing comment on the line, kept across edits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
     pub source_file: AliasSource,
+    #[serde(default)]
+    pub kind: AliasKind,
+    /// Defined outside the managed rc file and shown read-only.
+    #[serde(default)]
+    pub external: bool,
+}
+
+/// Zsh's alias flavours. Bash only has regular aliases.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AliasKind {
+    /// Expanded in command position only.
+    #[default]
+    Regular,
+    /// `alias -g`: expanded anywhere on the command line.
+    Global,
+    /// `alias -s`: runs the command on files with the given extension.
+    Suffix,
+}
+
+impl AliasKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AliasKind::Regular => "regular",
+            AliasKind::Global => "global",
+            AliasKind::Suffix => "suffix",
+        }
+    }
+
+    /// Accepts the full name or its first letter.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "r" | "regular" => Some(AliasKind::Regular),
+            "g" | "global" => Some(AliasKind::Global),
+            "s" | "suffix" => Some(AliasKind::Suffix),
+            _ => None,
+        }
+    }
+
+    fn flag(&self) -> Option<&'static str> {
+        match self {
+            AliasKind::Regular => None,
+            AliasKind::Global => Some("-g"),
+            AliasKind::Suffix => Some("-s"),
+        }
+    }
+
+    fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "-r" => Some(AliasKind::Regular),
+            "-g" => Some(AliasKind::Global),
+            "-s" => Some(AliasKind::Suffix),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -45,48 +105,124 @@ impl Alias {
             name,
             command,
             description: None,
-            created_at: now,
-            updated_at: now,
+            created_at: Some(now),
+            updated_at: Some(now),
+            comment: None,
             source_file: source,
+            kind: AliasKind::Regular,
+            external: false,
         }
     }
 
+    pub fn with_kind(mut self, kind: AliasKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
     pub fn with_description(mut self, description: String) -> Self {
         self.description = Some(description);
         self
     }
 
+    /// Renders the definition line. The kind flag is only written for zsh,
+    /// since `alias -g` is an error in bash.
     pub fn to_alias_string(&self) -> String {
-        format!("alias {}='{}'", self.name, self.command)
+        let flag = match (&self.source_file, self.kind.flag()) {
+            (AliasSource::Zsh, Some(flag)) => format!("{} ", flag),
+            _ => String::new(),
+        };
+        let metadata = Metadata {
+            description: self.description.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            comment: self.comment.clone(),
+        };
+        format!(
+            "alias {}{}={}{}",
+            flag,
+            self.name,
+            shell::single_quote(&self.command),
+            metadata.render()
+        )
+    }
+
+    /// Checks that the definition renders as a line the shell will accept.
+    ///
+    /// Names follow bash's rules: no whitespace, quoting characters, `=`,
+    /// `$`, backquotes or metacharacters, and no `/`, which zsh allows.
+    /// Commands are always single-quoted, so only newlines are a problem.
+    pub fn validate(&self) -> Result<(), String> {
+        let forbidden = |c: char| {
+            c.is_whitespace()
+                || matches!(
+                    c,
+                    '=' | '\'' | '"' | '\\' | '`' | '$' | '|' | '&' | ';' | '(' | ')' | '<' | '>'
+                )
+                || (c == '/' && self.source_file == AliasSource::Bash)
+        };
+
+        if self.name.is_empty() {
+            return Err("Alias name can't be empty".to_string());
+        }
+        if self.name.starts_with('-') {
+            return Err(format!("Alias name '{}' can't start with '-'", self.name));
+        }
+        if let Some(c) = self.name.chars().find(|c| forbidden(*c)) {
+            return Err(format!(
+                "Alias name '{}' can't contain {:?} in {}",
+                self.name,
+                c,
+                self.source_file.as_str()
+            ));
+        }
+        if self.kind != AliasKind::Regular && self.source_file != AliasSource::Zsh {
+            return Err(format!(
+                "{} aliases need zsh, not {}",
+                self.kind.as_str(),
+                self.source_file.as_str()
+            ));
+        }
+        if self.kind == AliasKind::Suffix && self.name.contains('.') {
+            return Err("Suffix aliases take an extension without the dot".to_string());
+        }
+        if self.command.contains('\n') {
+            return Err("Alias commands must fit on one line; use a function instead".to_string());
+        }
+        Ok(())
     }
 
-    pub fn parse_line(line: &str) -> Option<Self> {
+    /// Parses an `alias` line from a file or listing of `source`'s shell.
+    pub fn parse_line(line: &str, source: AliasSource) -> Option<Self> {
         let trimmed = line.trim();
         if !trimmed.starts_with("alias ") {
             return None;
         }
 
-        let rest = trimmed.strip_prefix("alias ")?;
+        let mut rest = trimmed.strip_prefix("alias ")?.trim_start();
+        let mut kind = AliasKind::Regular;
+        if rest.starts_with('-') {
+            let (flag, after) = rest.split_once(char::is_whitespace)?;
+            kind = AliasKind::from_flag(flag)?;
+            rest = after.trim_start();
+        }
+
         let eq_pos = rest.find('=')?;
         let name = rest[..eq_pos].trim().to_string();
+        let (command, remainder) = shell::split_word(&rest[eq_pos + 1..])?;
 
-        let command_part = &rest[eq_pos + 1..];
-        let command = if (command_part.starts_with('\'') && command_part.ends_with('\''))
-            || (command_part.starts_with('"') && command_part.ends_with('"'))
-        {
-            command_part[1..command_part.len() - 1].to_string()
-        } else {
-            command_part.to_string()
-        };
+        let metadata = Metadata::parse(remainder);
 
         Some(Self {
             id: Uuid::new_v4(),
             name,
             command,
-            description: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-            source_file: AliasSource::Bash,
+            description: metadata.description,
+            created_at: metadata.created_at,
+            updated_at: metadata.updated_at,
+            comment: metadata.comment,
+            source_file: source,
+            kind,
+            external: false,
         })
     }
 
@@ -101,3 +237,89 @@ impl Alias {
                 .unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_description_and_timestamps_round_trip() {
+        let alias = Alias::new(
+            "gs".to_string(),
+            "git status -sb".to_string(),
+            AliasSource::Bash,
+        )
+        .with_description("Short \"status\"".to_string());
+
+        let line = alias.to_alias_string();
+        let parsed = Alias::parse_line(&line, AliasSource::Bash).unwrap();
+
+        assert_eq!(parsed.name, "gs");
+        assert_eq!(parsed.command, "git status -sb");
+        assert_eq!(parsed.description.as_deref(), Some("Short \"status\""));
+        assert_eq!(
+            parsed.created_at.map(|t| t.timestamp()),
+            alias.created_at.map(|t| t.timestamp())
+        );
+        assert_eq!(parsed.to_alias_string(), line);
+    }
+
+    #[test]
+    fn test_zsh_kinds_round_trip() {
+        let parsed = Alias::parse_line("alias -s md='glow'", AliasSource::Zsh).unwrap();
+        assert_eq!(parsed.kind, AliasKind::Suffix);
+        assert_eq!(parsed.name, "md");
+        assert!(parsed.to_alias_string().starts_with("alias -s md='glow'"));
+
+        let global = Alias::parse_line("alias -g G='| grep'", AliasSource::Zsh).unwrap();
+        assert_eq!(global.kind, AliasKind::Global);
+        assert!(Alias::parse_line("alias -L", AliasSource::Zsh).is_none());
+    }
+
+    #[test]
+    fn test_validate_rejects_names_that_break_the_rc_file() {
+        let alias = |name: &str, source| Alias::new(name.to_string(), "true".to_string(), source);
+        assert!(alias("gs", AliasSource::Bash).validate().is_ok());
+        assert!(alias("g s", AliasSource::Bash).validate().is_err());
+        assert!(alias("a=b", AliasSource::Zsh).validate().is_err());
+        assert!(alias("it's", AliasSource::Zsh).validate().is_err());
+        assert!(alias("../", AliasSource::Bash).validate().is_err());
+        assert!(alias("../", AliasSource::Zsh).validate().is_ok());
+        assert!(alias("G", AliasSource::Bash)
+            .with_kind(AliasKind::Global)
+            .validate()
+            .is_err());
+        assert!(alias("G", AliasSource::Zsh)
+            .with_kind(AliasKind::Global)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_parse_plain_alias_ignores_other_comments() {
+        let parsed =
+            Alias::parse_line("  alias ll=\"ls -la\" # long listing", AliasSource::Bash).unwrap();
+        assert_eq!(parsed.command, "ls -la");
+        assert_eq!(parsed.description, None);
+    }
+
+    #[test]
+    fn test_plain_comment_is_kept_and_no_dates_are_made_up() {
+        let line = "alias ll='ls -la'  # my note";
+        let parsed = Alias::parse_line(line, AliasSource::Bash).unwrap();
+        assert_eq!(parsed.created_at, None);
+        assert_eq!(parsed.to_alias_string(), line);
+
+        let mut edited = parsed.clone();
+        edited.command = "ls -lah".to_string();
+        edited.description = Some("Long listing".to_string());
+        let line = edited.to_alias_string();
+        assert_eq!(
+            line,
+            "alias ll='ls -lah'  # my note  # sniplias: desc=\"Long listing\""
+        );
+        let reparsed = Alias::parse_line(&line, AliasSource::Bash).unwrap();
+        assert_eq!(reparsed.comment.as_deref(), Some("# my note"));
+        assert_eq!(reparsed.description.as_deref(), Some("Long listing"));
+    }
+}
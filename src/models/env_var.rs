@@ -0,0 +1,268 @@
+use super::alias::AliasSource;
+use super::metadata::Metadata;
+use super::shell::double_quote;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Name fragments that mark a variable as holding a credential.
+const SECRET_MARKERS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+pub const MASKED_VALUE: &str = "••••••••";
+
+/// An `export NAME=value` line.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EnvVar {
+    pub id: Uuid,
+    pub name: String,
+    /// The value with `$HOME` and friends left for the shell to expand.
+    /// Everything else is literal.
+    pub value: String,
+    pub description: Option<String>,
+    /// None for a line sniplias hasn't stamped yet.
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// The user's own trailing comment on the line, kept across edits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub source_file: AliasSource,
+}
+
+impl EnvVar {
+    pub fn new(name: String, value: String, source: AliasSource) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            value,
+            description: None,
+            created_at: Some(now),
+            updated_at: Some(now),
+            comment: None,
+            source_file: source,
+        }
+    }
+
+    pub fn to_export_string(&self) -> String {
+        let metadata = Metadata {
+            description: self.description.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            comment: self.comment.clone(),
+        };
+        format!(
+            "export {}={}{}",
+            self.name,
+            double_quote(&self.value),
+            metadata.render()
+        )
+    }
+
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let rest = line.trim().strip_prefix("export ")?.trim_start();
+        let eq_pos = rest.find('=')?;
+        let name = &rest[..eq_pos];
+        if !is_valid_name(name) {
+            return None;
+        }
+
+        let (value, remainder) = expanding_value(&rest[eq_pos + 1..])?;
+        let metadata = Metadata::parse(remainder);
+
+        Some(Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            value,
+            description: metadata.description,
+            created_at: metadata.created_at,
+            updated_at: metadata.updated_at,
+            comment: metadata.comment,
+            source_file: AliasSource::Bash,
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if is_valid_name(&self.name) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Variable name '{}' must be letters, digits and '_', not starting with a digit",
+                self.name
+            ))
+        }
+    }
+
+    /// Whether the value is a `:`-separated list such as `PATH` or `MANPATH`.
+    pub fn is_path_list(&self) -> bool {
+        self.name.ends_with("PATH") || self.name.ends_with("DIRS")
+    }
+
+    pub fn entries(&self) -> Vec<&str> {
+        self.value.split(':').filter(|e| !e.is_empty()).collect()
+    }
+
+    pub fn set_entries<S: AsRef<str>>(&mut self, entries: &[S]) {
+        self.value = entries
+            .iter()
+            .map(|e| e.as_ref().trim())
+            .filter(|e| !e.is_empty())
+            .collect::<Vec<_>>()
+            .join(":");
+    }
+
+    pub fn looks_secret(&self) -> bool {
+        name_looks_secret(&self.name)
+    }
+
+    /// `line` with its value hidden if it exports a variable whose name
+    /// suggests a credential, for showing rc file text such as a diff.
+    pub fn mask_line(line: &str) -> Option<String> {
+        let trimmed = line.trim_start();
+        let (name, _) = trimmed.strip_prefix("export ")?.split_once('=')?;
+        let name = name.trim_start();
+        (is_valid_name(name) && name_looks_secret(name)).then(|| {
+            let indent = &line[..line.len() - trimmed.len()];
+            format!("{}export {}={}", indent, name, MASKED_VALUE)
+        })
+    }
+
+    /// The value for display, hidden if the name suggests a credential.
+    pub fn display_value(&self) -> &str {
+        if self.looks_secret() {
+            MASKED_VALUE
+        } else {
+            &self.value
+        }
+    }
+
+    pub fn matches_search(&self, query: &str) -> bool {
+        let query_lower = query.to_lowercase();
+        self.name.to_lowercase().contains(&query_lower)
+            || (!self.looks_secret() && self.value.to_lowercase().contains(&query_lower))
+            || self
+                .description
+                .as_ref()
+                .map(|d| d.to_lowercase().contains(&query_lower))
+                .unwrap_or(false)
+    }
+}
+
+fn name_looks_secret(name: &str) -> bool {
+    let upper = name.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Reads one shell word, undoing quotes and escapes but leaving `$` and
+/// backticks to expand. `None` if the word has a literal `$` or backtick,
+/// which double quotes can't write back, or an open quote.
+fn expanding_value(input: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => return Some((value, &input[i..])),
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        (_, '\'') => break,
+                        (_, '$' | '`') => return None,
+                        (_, c) => value.push(c),
+                    }
+                }
+            }
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        (_, '"') => break,
+                        (_, '\\') => match chars.next()? {
+                            (_, '$' | '`') => return None,
+                            (_, c @ ('"' | '\\')) => value.push(c),
+                            (_, c) => {
+                                value.push('\\');
+                                value.push(c);
+                            }
+                        },
+                        (_, c) => value.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                chars.next();
+                match chars.next() {
+                    Some((_, '$' | '`')) => return None,
+                    Some((_, c)) => value.push(c),
+                    None => {}
+                }
+            }
+            c => {
+                chars.next();
+                value.push(c);
+            }
+        }
+    }
+
+    Some((value, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keeps_references_and_literals_apart() {
+        let var = EnvVar::parse_line(r#"export PATH="$HOME/bin:$PATH""#).unwrap();
+        assert_eq!(var.value, "$HOME/bin:$PATH");
+        assert_eq!(var.entries(), ["$HOME/bin", "$PATH"]);
+
+        let var = EnvVar::parse_line(r#"export GREETING='say "hi" \'"#).unwrap();
+        assert_eq!(var.value, r#"say "hi" \"#);
+        assert_eq!(
+            var.to_export_string().split("  ").next().unwrap(),
+            r#"export GREETING="say \"hi\" \\""#
+        );
+        assert_eq!(
+            EnvVar::parse_line(&var.to_export_string()).unwrap().value,
+            var.value
+        );
+
+        // A literal `$` can't be written inside double quotes; leave it be.
+        assert!(EnvVar::parse_line("export PS1='$ '").is_none());
+        assert!(EnvVar::parse_line("export -f myfunc").is_none());
+        assert!(EnvVar::parse_line("export 1X=y").is_none());
+    }
+
+    #[test]
+    fn test_path_entries_and_masking() {
+        let mut var = EnvVar::new("PATH".into(), "/a:/b".into(), AliasSource::Bash);
+        var.set_entries(&["/c", "/a", " ", "/b"]);
+        assert_eq!(var.value, "/c:/a:/b");
+        assert_eq!(
+            EnvVar::parse_line(&var.to_export_string()).unwrap().value,
+            var.value
+        );
+
+        let token = EnvVar::new("GITHUB_TOKEN".into(), "ghp_x".into(), AliasSource::Bash);
+        assert_eq!(token.display_value(), MASKED_VALUE);
+        assert!(!token.matches_search("ghp"));
+    }
+}
@@ -0,0 +1,366 @@
+use super::alias::AliasSource;
+use super::metadata::{Metadata, METADATA_MARKER};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// A shell function defined with `name() { ... }` or `function name { ... }`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ShellFunction {
+    pub id: Uuid,
+    pub name: String,
+    /// The commands between the braces, without the common indentation.
+    pub body: String,
+    pub description: Option<String>,
+    /// None for a definition sniplias hasn't stamped yet.
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub source_file: AliasSource,
+}
+
+fn header_regex() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?:function\s+([A-Za-z_][\w.:-]*)\s*(?:\(\s*\))?|([A-Za-z_][\w.:-]*)\s*\(\s*\))\s*(\{)?(.*)$",
+        )
+        .unwrap()
+    })
+}
+
+fn heredoc_regex() -> &'static Regex {
+    static HEREDOC: OnceLock<Regex> = OnceLock::new();
+    HEREDOC.get_or_init(|| {
+        Regex::new(r#"<<(-?)\s*(?:'([^']+)'|"([^"]+)"|\\?([A-Za-z_]\w*))"#).unwrap()
+    })
+}
+
+impl ShellFunction {
+    pub fn new(name: String, body: String, source: AliasSource) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            body,
+            description: None,
+            created_at: Some(now),
+            updated_at: Some(now),
+            source_file: source,
+        }
+    }
+
+    pub fn to_function_string(&self) -> String {
+        let metadata = Metadata {
+            description: self.description.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            comment: None,
+        };
+        let mut output = format!("{}() {{{}\n", self.name, metadata.render());
+        let mut heredocs = Heredocs::default();
+        for line in self.body.lines() {
+            if heredocs.contains(line) {
+                output.push_str(line);
+                output.push('\n');
+            } else if line.trim().is_empty() {
+                output.push('\n');
+            } else {
+                output.push_str("    ");
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+        output.push('}');
+        output
+    }
+
+    /// Parses a function whose header is `lines[0]`. Returns the function and
+    /// the number of lines its definition spans.
+    pub fn parse_lines(lines: &[&str]) -> Option<(Self, usize)> {
+        let caps = header_regex().captures(lines.first()?)?;
+        let name = caps.get(1).or_else(|| caps.get(2))?.as_str().to_string();
+
+        // The opening brace may sit on the header line or on the next one.
+        let (first_body, mut consumed) = if caps.get(3).is_some() {
+            (caps.get(4).map_or("", |m| m.as_str()), 1)
+        } else {
+            let rest = caps.get(4).map_or("", |m| m.as_str()).trim();
+            if !rest.is_empty() {
+                return None;
+            }
+            (lines.get(1)?.trim_start().strip_prefix('{')?, 2)
+        };
+
+        // Any other comment after the brace is part of the body.
+        let (metadata, mut line) = if first_body.trim_start().starts_with(METADATA_MARKER) {
+            (Metadata::parse(first_body), "")
+        } else {
+            (Metadata::default(), first_body)
+        };
+        let mut body_lines = Vec::new();
+        let mut depth = 1usize;
+        let mut heredocs = Heredocs::default();
+
+        loop {
+            if heredocs.contains(line) {
+                body_lines.push(line);
+                line = lines.get(consumed)?;
+                consumed += 1;
+                continue;
+            }
+            match closing_brace(line, &mut depth) {
+                Some(end) => {
+                    body_lines.push(&line[..end]);
+                    break;
+                }
+                None => body_lines.push(line),
+            }
+            line = lines.get(consumed)?;
+            consumed += 1;
+        }
+
+        let function = Self {
+            id: Uuid::new_v4(),
+            name,
+            body: dedent(&body_lines),
+            description: metadata.description,
+            created_at: metadata.created_at,
+            updated_at: metadata.updated_at,
+            source_file: AliasSource::Bash,
+        };
+        Some((function, consumed))
+    }
+
+    /// Checks that the name is one the header parser reads back: a letter
+    /// or `_`, then letters, digits, `_`, `.`, `:` or `-`.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut chars = self.name.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'));
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "Function name '{}' must start with a letter or '_' and contain only letters, digits, '_', '.', ':' or '-'",
+                self.name
+            ))
+        }
+    }
+
+    pub fn matches_search(&self, query: &str) -> bool {
+        let query_lower = query.to_lowercase();
+        self.name.to_lowercase().contains(&query_lower)
+            || self.body.to_lowercase().contains(&query_lower)
+            || self
+                .description
+                .as_ref()
+                .map(|d| d.to_lowercase().contains(&query_lower))
+                .unwrap_or(false)
+    }
+}
+
+/// Tracks brace depth through `line`, skipping quoted text and comments.
+/// Returns the byte offset of the brace that closes the function, if any.
+fn closing_brace(line: &str, depth: &mut usize) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut prev = ' ';
+
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            } else if c == '\\' && q == '"' {
+                escaped = true;
+            }
+        } else {
+            match c {
+                '\\' => escaped = true,
+                '\'' | '"' => quote = Some(c),
+                '#' if prev.is_whitespace() || prev == ';' => return None,
+                '{' => *depth += 1,
+                '}' => {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Follows the heredocs opened in a function body, line by line.
+#[derive(Default)]
+struct Heredocs {
+    /// Delimiters still to be reached, and whether each allows leading tabs
+    /// (`<<-`).
+    pending: VecDeque<(String, bool)>,
+}
+
+impl Heredocs {
+    /// Whether `line` is heredoc text or the line ending a heredoc. Those
+    /// are kept exactly as written, since indenting them would change the
+    /// text or hide the delimiter.
+    fn contains(&mut self, line: &str) -> bool {
+        if let Some((delimiter, tabs)) = self.pending.front() {
+            let end = if *tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if end == delimiter {
+                self.pending.pop_front();
+            }
+            return true;
+        }
+        // `<<<` is a here-string, which doesn't span lines.
+        let line = line.replace("<<<", "   ");
+        for caps in heredoc_regex().captures_iter(&line) {
+            if let Some(delimiter) = caps.get(2).or(caps.get(3)).or(caps.get(4)) {
+                self.pending
+                    .push_back((delimiter.as_str().to_string(), !caps[1].is_empty()));
+            }
+        }
+        false
+    }
+}
+
+/// Joins `lines`, dropping blank edges and the indentation they all share.
+/// Heredoc lines are left as they are.
+fn dedent(lines: &[&str]) -> String {
+    let mut heredocs = Heredocs::default();
+    let trimmed: Vec<(&str, bool)> = lines
+        .iter()
+        .map(|l| {
+            if heredocs.contains(l) {
+                (*l, true)
+            } else {
+                (l.trim_end(), false)
+            }
+        })
+        .collect();
+    let start = trimmed
+        .iter()
+        .position(|(l, _)| !l.is_empty())
+        .unwrap_or(trimmed.len());
+    let end = trimmed
+        .iter()
+        .rposition(|(l, _)| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    let lines = &trimmed[start..end];
+
+    let indent = lines
+        .iter()
+        .filter(|(l, verbatim)| !verbatim && !l.is_empty())
+        .map(|(l, _)| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|(l, verbatim)| {
+            if *verbatim || l.is_empty() {
+                *l
+            } else {
+                &l[indent..]
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end_matches(';')
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_function_forms() {
+        let lines = [
+            "mkcd() {",
+            "  mkdir -p \"$1\" && cd \"$1\"  # make and enter",
+            "  echo \"}\"",
+            "}",
+            "alias after='x'",
+        ];
+        let (function, consumed) = ShellFunction::parse_lines(&lines).unwrap();
+        assert_eq!(function.name, "mkcd");
+        assert_eq!(
+            function.body,
+            "mkdir -p \"$1\" && cd \"$1\"  # make and enter\necho \"}\""
+        );
+        assert_eq!(consumed, 4);
+
+        let (function, consumed) =
+            ShellFunction::parse_lines(&["function greet { echo \"hi ${1:-you}\"; }"]).unwrap();
+        assert_eq!(function.name, "greet");
+        assert_eq!(function.body, "echo \"hi ${1:-you}\"");
+        assert_eq!(consumed, 1);
+
+        let (function, consumed) =
+            ShellFunction::parse_lines(&["up ()", "{", "  cd ..", "}"]).unwrap();
+        assert_eq!(function.name, "up");
+        assert_eq!(consumed, 4);
+
+        assert!(ShellFunction::parse_lines(&["echo () not a function"]).is_none());
+    }
+
+    #[test]
+    fn test_function_round_trips() {
+        let mut function = ShellFunction::new(
+            "mkcd".to_string(),
+            "mkdir -p \"$1\"\ncd \"$1\"".to_string(),
+            AliasSource::Bash,
+        );
+        function.description = Some("Make and enter".to_string());
+
+        let rendered = function.to_function_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        let (parsed, consumed) = ShellFunction::parse_lines(&lines).unwrap();
+
+        assert_eq!(consumed, lines.len());
+        assert_eq!(parsed.body, function.body);
+        assert_eq!(parsed.description, function.description);
+        assert_eq!(parsed.to_function_string(), rendered);
+    }
+
+    #[test]
+    fn test_heredocs_are_kept_verbatim() {
+        let lines = [
+            "usage() {",
+            "    cat <<EOF",
+            "usage: tool {start|stop}",
+            "  indented",
+            "EOF",
+            "    cat <<-'END'",
+            "\t}",
+            "\tEND",
+            "}",
+        ];
+        let (function, consumed) = ShellFunction::parse_lines(&lines).unwrap();
+        assert_eq!(consumed, lines.len());
+        assert_eq!(
+            function.body,
+            "cat <<EOF\nusage: tool {start|stop}\n  indented\nEOF\ncat <<-'END'\n\t}\n\tEND"
+        );
+
+        let rendered = function.to_function_string();
+        assert!(rendered.contains("\nusage: tool {start|stop}\n  indented\nEOF\n"));
+        let lines: Vec<&str> = rendered.lines().collect();
+        let (parsed, _) = ShellFunction::parse_lines(&lines).unwrap();
+        assert_eq!(parsed.body, function.body);
+    }
+}
@@ -0,0 +1,92 @@
+use super::shell;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Prefix of the trailing comment that carries fields the shell has no place for.
+pub const METADATA_MARKER: &str = "# sniplias:";
+
+/// Description and timestamps kept in a `# sniplias: ...` comment next to
+/// definitions in rc files, along with any comment of the user's own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub description: Option<String>,
+    /// None until sniplias has seen the definition being created.
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// A trailing `# ...` comment that isn't ours, kept as written.
+    pub comment: Option<String>,
+}
+
+impl Metadata {
+    /// Renders what follows a definition on its line: the user's comment,
+    /// then `# sniplias: desc="..." created=... updated=...` with the
+    /// fields that are known. Empty if there is nothing to keep.
+    pub fn render(&self) -> String {
+        let mut fields = Vec::new();
+        if let Some(description) = &self.description {
+            fields.push(format!("desc={}", shell::double_quote(description)));
+        }
+        if let Some(created_at) = self.created_at {
+            fields.push(format!(
+                "created={}",
+                created_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ));
+        }
+        if let Some(updated_at) = self.updated_at {
+            fields.push(format!(
+                "updated={}",
+                updated_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ));
+        }
+
+        let mut trailer = String::new();
+        if let Some(comment) = &self.comment {
+            trailer.push_str("  ");
+            trailer.push_str(comment);
+        }
+        if !fields.is_empty() {
+            trailer.push_str(&format!("  {} {}", METADATA_MARKER, fields.join(" ")));
+        }
+        trailer
+    }
+
+    /// Reads the fields from whatever follows a definition on its line. A
+    /// comment before the sniplias one, or instead of it, is kept as is.
+    pub fn parse(trailer: &str) -> Self {
+        let (comment, fields) = match trailer.find(METADATA_MARKER) {
+            Some(i) => (&trailer[..i], Some(&trailer[i + METADATA_MARKER.len()..])),
+            None => (trailer, None),
+        };
+        let comment = comment.trim();
+        let mut metadata = Self {
+            comment: comment.starts_with('#').then(|| comment.to_string()),
+            ..Self::default()
+        };
+        let Some(mut rest) = fields else {
+            return metadata;
+        };
+
+        loop {
+            rest = rest.trim_start();
+            let Some(eq_pos) = rest.find('=') else {
+                break;
+            };
+            let key = &rest[..eq_pos];
+            let Some((value, remainder)) = shell::split_word(&rest[eq_pos + 1..]) else {
+                break;
+            };
+            rest = remainder;
+
+            let timestamp = DateTime::parse_from_rfc3339(&value)
+                .ok()
+                .map(|t| t.with_timezone(&Utc));
+            match key {
+                "desc" if !value.is_empty() => metadata.description = Some(value),
+                "created" => metadata.created_at = timestamp,
+                "updated" => metadata.updated_at = timestamp,
+                _ => {}
+            }
+        }
+
+        metadata
+    }
+}
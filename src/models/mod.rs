@@ -1,5 +1,11 @@
 mod alias;
+mod env_var;
+mod function;
+mod metadata;
+mod shell;
 mod snippet;
 
-pub use alias::{Alias, AliasSource};
+pub use alias::{Alias, AliasKind, AliasSource};
+pub use env_var::EnvVar;
+pub use function::ShellFunction;
 pub use snippet::Snippet;
@@ -0,0 +1,89 @@
+//! Just enough POSIX shell quoting to read and write single-line definitions.
+
+/// Reads one shell word from the start of `input`, undoing quotes and
+/// backslash escapes. Returns the word and whatever follows it, or `None`
+/// if a quote is left open.
+pub fn split_word(input: &str) -> Option<(String, &str)> {
+    let mut word = String::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => return Some((word, &input[i..])),
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        (_, '\'') => break,
+                        (_, c) => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        (_, '"') => break,
+                        (_, '\\') => match chars.next()? {
+                            (_, c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            (_, c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        (_, c) => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                chars.next();
+                if let Some((_, c)) = chars.next() {
+                    word.push(c);
+                }
+            }
+            c => {
+                chars.next();
+                word.push(c);
+            }
+        }
+    }
+
+    Some((word, ""))
+}
+
+/// Wraps `value` in single quotes, escaping any single quotes inside it.
+pub fn single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Wraps `value` in double quotes, escaping only `"` and `\`. Used for our
+/// own comment metadata, which the shell never evaluates.
+pub fn double_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', r"\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_word_handles_quotes_and_escapes() {
+        assert_eq!(
+            split_word(r"'it'\''s' # rest"),
+            Some(("it's".to_string(), " # rest"))
+        );
+        assert_eq!(
+            split_word(r#""say \"hi\" $HOME" tail"#),
+            Some((r#"say "hi" $HOME"#.to_string(), " tail"))
+        );
+        assert_eq!(split_word("ls"), Some(("ls".to_string(), "")));
+        assert_eq!(split_word("'open"), None);
+    }
+
+    #[test]
+    fn test_single_quote_round_trips() {
+        let value = "echo 'quoted' \"and\" $VAR";
+        let quoted = single_quote(value);
+        assert_eq!(split_word(&quoted), Some((value.to_string(), "")));
+    }
+}
@@ -1,16 +1,36 @@
 use chrono::{DateTime, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Snippet {
+    #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub title: String,
     pub description: Option<String>,
     pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Shell commands that list choices for a variable, kept from navi's
+    /// `$ var: command` lines. sniplias doesn't run them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub generators: BTreeMap<String, String>,
+    /// The tool the snippet was imported from, e.g. `navi`. Importing again
+    /// only updates snippets that have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
+    #[serde(default = "Utc::now")]
     pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
     pub updated_at: DateTime<Utc>,
+    /// Name of the library the snippet belongs to; empty means the default.
+    #[serde(skip)]
+    pub library: String,
+    /// Fields this version doesn't know about, kept so saving doesn't drop them.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -27,8 +47,13 @@ impl Snippet {
             title,
             description: None,
             command,
+            tags: Vec::new(),
+            generators: BTreeMap::new(),
+            imported_from: None,
             created_at: now,
             updated_at: now,
+            library: String::new(),
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -85,6 +110,10 @@ impl Snippet {
                 .map(|d| d.to_lowercase().contains(&query_lower))
                 .unwrap_or(false)
             || self.command.to_lowercase().contains(&query_lower)
+            || self
+                .tags
+                .iter()
+                .any(|t| t.to_lowercase().contains(&query_lower))
     }
 }
 
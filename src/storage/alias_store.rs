@@ -1,9 +1,16 @@
-use crate::models::{Alias, AliasSource};
+use super::diff::unified_diff;
+use super::generated;
+use super::rc_file::{RcEdit, RcFile};
+use super::stamp::FileStamp;
+use super::{atomic_write, AliasMode, BackupEntry, Backups, Config};
+use crate::models::{Alias, AliasSource, EnvVar, ShellFunction};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
+use uuid::Uuid;
 
 #[derive(Error, Debug)]
 pub enum AliasStoreError {
@@ -11,30 +18,433 @@ pub enum AliasStoreError {
     ReadError(#[source] std::io::Error),
     #[error("Failed to write shell config file: {0}")]
     WriteError(#[source] std::io::Error),
+    #[error("Failed to back up shell config file: {0}")]
+    BackupError(#[source] std::io::Error),
     #[error("Failed to determine shell config path")]
     ConfigPathNotFound,
     #[error("Alias not found: {0}")]
     AliasNotFound(String),
+    #[error("Function not found: {0}")]
+    FunctionNotFound(String),
+    #[error("Environment variable not found: {0}")]
+    EnvVarNotFound(String),
+    #[error("{} was changed by another program", .0.display())]
+    Conflict(PathBuf),
+    #[error("{0}")]
+    InvalidAlias(String),
+    #[error("{shell} rejected the generated lines: {message}")]
+    SyntaxError { shell: String, message: String },
+    #[error("Shell config unavailable: {0}")]
+    Unavailable(String),
+    #[error("Failed to read config: {0}")]
+    ConfigError(#[source] std::io::Error),
+    #[error("Failed to add the source line to {}: {source}", .path.display())]
+    HookError {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
 }
 
-pub struct AliasStore {
+/// One mutation of the aliases, functions and exports in the rc file, kept as a value
+/// so it can be re-applied on top of a freshly reloaded file when a save
+/// runs into a conflict.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AliasChange {
+    Add(Alias),
+    Update {
+        name: String,
+        alias: Alias,
+    },
+    Delete(String),
+    AddFunction(ShellFunction),
+    UpdateFunction {
+        name: String,
+        function: ShellFunction,
+    },
+    DeleteFunction(String),
+    AddEnv(EnvVar),
+    /// Exports are found by name and value, since a name like `PATH` is
+    /// often exported more than once.
+    UpdateEnv {
+        old: EnvVar,
+        var: EnvVar,
+    },
+    DeleteEnv(EnvVar),
+    /// Several changes written in one save, e.g. adopting imported aliases.
+    Batch(Vec<AliasChange>),
+}
+
+impl AliasChange {
+    /// Whether it writes or removes an export that looks like a credential.
+    pub fn touches_secret(&self) -> bool {
+        match self {
+            AliasChange::AddEnv(var) | AliasChange::DeleteEnv(var) => var.looks_secret(),
+            AliasChange::UpdateEnv { old, var } => old.looks_secret() || var.looks_secret(),
+            AliasChange::Batch(changes) => changes.iter().any(AliasChange::touches_secret),
+            _ => false,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            AliasChange::Add(alias) => format!("Alias '{}' added", alias.name),
+            AliasChange::Update { alias, .. } => format!("Alias '{}' updated", alias.name),
+            AliasChange::Delete(name) => format!("Alias '{}' deleted", name),
+            AliasChange::AddFunction(function) => format!("Function '{}' added", function.name),
+            AliasChange::UpdateFunction { function, .. } => {
+                format!("Function '{}' updated", function.name)
+            }
+            AliasChange::DeleteFunction(name) => format!("Function '{}' deleted", name),
+            AliasChange::AddEnv(var) => format!("Variable '{}' added", var.name),
+            AliasChange::UpdateEnv { var, .. } => format!("Variable '{}' updated", var.name),
+            AliasChange::DeleteEnv(var) => format!("Variable '{}' deleted", var.name),
+            AliasChange::Batch(changes) => match changes.as_slice() {
+                [change] => change.describe(),
+                changes => format!("{} changes saved", changes.len()),
+            },
+        }
+    }
+}
+
+/// A definition found while scanning an rc file.
+enum Definition {
+    Alias(Alias),
+    Function(ShellFunction),
+    EnvVar(EnvVar),
+}
+
+/// Everything sniplias manages in the rc file. Aliases and functions are
+/// keyed by name; every export is kept, in file order.
+#[derive(Debug, Clone, Default)]
+struct Definitions {
     aliases: HashMap<String, Alias>,
+    functions: HashMap<String, ShellFunction>,
+    env_vars: Vec<EnvVar>,
+}
+
+/// Orders new definitions in the managed block: exports first, in the order
+/// they were added, then aliases, then functions, each by name.
+type BlockOrder<'a> = (u8, &'a str, usize);
+
+impl Definitions {
+    /// The rc file text of every definition, by id, along with where it
+    /// goes if it is new.
+    fn rendered(&self) -> HashMap<Uuid, (BlockOrder<'_>, String)> {
+        let env_vars = self
+            .env_vars
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.id, ((0, "", i), v.to_export_string())));
+        let aliases = self
+            .aliases
+            .values()
+            .map(|a| (a.id, ((1, a.name.as_str(), 0), a.to_alias_string())));
+        let functions = self
+            .functions
+            .values()
+            .map(|f| (f.id, ((2, f.name.as_str(), 0), f.to_function_string())));
+        env_vars.chain(aliases).chain(functions).collect()
+    }
+
+    /// The id currently holding `name` for the kind of definition in `def`.
+    /// Exports are matched by name and value, skipping ids in `taken`.
+    fn id_of(&self, def: &Definition, taken: &[Uuid]) -> Option<Uuid> {
+        match def {
+            Definition::Alias(a) => self.aliases.get(&a.name).map(|a| a.id),
+            Definition::Function(f) => self.functions.get(&f.name).map(|f| f.id),
+            Definition::EnvVar(v) => self
+                .env_vars
+                .iter()
+                .find(|e| e.name == v.name && e.value == v.value && !taken.contains(&e.id))
+                .map(|e| e.id),
+        }
+    }
+
+    /// Where the export of `var`'s name and value is, the last one if there
+    /// are several.
+    fn env_index(&self, var: &EnvVar) -> Option<usize> {
+        self.env_vars
+            .iter()
+            .rposition(|e| e.name == var.name && e.value == var.value)
+    }
+
+    fn inverse(&self, change: &AliasChange) -> Option<AliasChange> {
+        Some(match change {
+            // Adding a name that exists replaces it, so undoing puts it back.
+            AliasChange::Add(alias) => match self.aliases.get(&alias.name) {
+                Some(existing) => AliasChange::Add(existing.clone()),
+                None => AliasChange::Delete(alias.name.clone()),
+            },
+            AliasChange::Update { name, alias } => AliasChange::Update {
+                name: alias.name.clone(),
+                alias: self.aliases.get(name)?.clone(),
+            },
+            AliasChange::Delete(name) => AliasChange::Add(self.aliases.get(name)?.clone()),
+            AliasChange::AddFunction(function) => match self.functions.get(&function.name) {
+                Some(existing) => AliasChange::AddFunction(existing.clone()),
+                None => AliasChange::DeleteFunction(function.name.clone()),
+            },
+            AliasChange::UpdateFunction { name, function } => AliasChange::UpdateFunction {
+                name: function.name.clone(),
+                function: self.functions.get(name)?.clone(),
+            },
+            AliasChange::DeleteFunction(name) => {
+                AliasChange::AddFunction(self.functions.get(name)?.clone())
+            }
+            AliasChange::AddEnv(var) => AliasChange::DeleteEnv(var.clone()),
+            AliasChange::UpdateEnv { old, var } => AliasChange::UpdateEnv {
+                old: var.clone(),
+                var: self.env_vars[self.env_index(old)?].clone(),
+            },
+            AliasChange::DeleteEnv(var) => {
+                AliasChange::AddEnv(self.env_vars[self.env_index(var)?].clone())
+            }
+            // Each step is undone against what the steps before it left.
+            AliasChange::Batch(changes) => {
+                let mut scratch = self.clone();
+                let mut inverses = Vec::with_capacity(changes.len());
+                for change in changes {
+                    inverses.push(scratch.inverse(change)?);
+                    scratch.apply(change.clone()).ok()?;
+                }
+                inverses.reverse();
+                AliasChange::Batch(inverses)
+            }
+        })
+    }
+
+    fn apply(&mut self, change: AliasChange) -> Result<(), AliasStoreError> {
+        match change {
+            AliasChange::Add(mut alias) => {
+                alias.validate().map_err(AliasStoreError::InvalidAlias)?;
+                // Re-adding an existing name rewrites its line instead of moving it.
+                if let Some(existing) = self.aliases.get(&alias.name) {
+                    alias.id = existing.id;
+                }
+                self.aliases.insert(alias.name.clone(), alias);
+            }
+            AliasChange::Update {
+                name,
+                alias: mut new_alias,
+            } => {
+                new_alias
+                    .validate()
+                    .map_err(AliasStoreError::InvalidAlias)?;
+                let old = self
+                    .aliases
+                    .remove(&name)
+                    .ok_or(AliasStoreError::AliasNotFound(name))?;
+                new_alias.id = old.id;
+                new_alias.created_at = old.created_at;
+                new_alias.comment = old.comment;
+                self.aliases.insert(new_alias.name.clone(), new_alias);
+            }
+            AliasChange::Delete(name) => {
+                if self.aliases.remove(&name).is_none() {
+                    return Err(AliasStoreError::AliasNotFound(name));
+                }
+            }
+            AliasChange::AddFunction(mut function) => {
+                function.validate().map_err(AliasStoreError::InvalidAlias)?;
+                if let Some(existing) = self.functions.get(&function.name) {
+                    function.id = existing.id;
+                }
+                self.functions.insert(function.name.clone(), function);
+            }
+            AliasChange::UpdateFunction {
+                name,
+                function: mut new_function,
+            } => {
+                new_function
+                    .validate()
+                    .map_err(AliasStoreError::InvalidAlias)?;
+                let old = self
+                    .functions
+                    .remove(&name)
+                    .ok_or(AliasStoreError::FunctionNotFound(name))?;
+                new_function.id = old.id;
+                new_function.created_at = old.created_at;
+                self.functions
+                    .insert(new_function.name.clone(), new_function);
+            }
+            AliasChange::DeleteFunction(name) => {
+                if self.functions.remove(&name).is_none() {
+                    return Err(AliasStoreError::FunctionNotFound(name));
+                }
+            }
+            AliasChange::AddEnv(var) => {
+                var.validate().map_err(AliasStoreError::InvalidAlias)?;
+                self.env_vars.push(var);
+            }
+            AliasChange::UpdateEnv {
+                old,
+                var: mut new_var,
+            } => {
+                new_var.validate().map_err(AliasStoreError::InvalidAlias)?;
+                let i = self
+                    .env_index(&old)
+                    .ok_or(AliasStoreError::EnvVarNotFound(old.name))?;
+                new_var.id = self.env_vars[i].id;
+                new_var.created_at = self.env_vars[i].created_at;
+                new_var.comment = self.env_vars[i].comment.take();
+                self.env_vars[i] = new_var;
+            }
+            AliasChange::DeleteEnv(var) => {
+                let i = self
+                    .env_index(&var)
+                    .ok_or(AliasStoreError::EnvVarNotFound(var.name))?;
+                self.env_vars.remove(i);
+            }
+            AliasChange::Batch(changes) => {
+                for change in changes {
+                    self.apply(change)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `change` adjusted to the definitions as they are now: updates of
+    /// something that has since disappeared become adds, and deletes of it
+    /// are dropped.
+    fn rebase(&self, change: AliasChange) -> Option<AliasChange> {
+        let change = match change {
+            AliasChange::Update { name, alias } if !self.aliases.contains_key(&name) => {
+                AliasChange::Add(alias)
+            }
+            AliasChange::Delete(name) if !self.aliases.contains_key(&name) => return None,
+            AliasChange::UpdateFunction { name, function }
+                if !self.functions.contains_key(&name) =>
+            {
+                AliasChange::AddFunction(function)
+            }
+            AliasChange::DeleteFunction(name) if !self.functions.contains_key(&name) => {
+                return None
+            }
+            AliasChange::UpdateEnv { old, var } if self.env_index(&old).is_none() => {
+                AliasChange::AddEnv(var)
+            }
+            AliasChange::DeleteEnv(var) if self.env_index(&var).is_none() => return None,
+            AliasChange::Batch(changes) => AliasChange::Batch(
+                changes
+                    .into_iter()
+                    .filter_map(|change| self.rebase(change))
+                    .collect(),
+            ),
+            change => change,
+        };
+        Some(change)
+    }
+}
+
+/// The rc file that sources the generated `aliases.sh`, and the fish
+/// variant written alongside it.
+#[derive(Debug, Clone)]
+struct Generated {
+    rc_path: PathBuf,
+    fish_path: PathBuf,
+}
+
+pub struct AliasStore {
+    definitions: Definitions,
     config_path: PathBuf,
     source: AliasSource,
+    document: RcFile,
+    /// Where each definition was found in `document` and how it rendered
+    /// there. A name defined more than once has every line, the one in
+    /// effect last.
+    origins: HashMap<Uuid, Vec<(Range<usize>, String)>>,
+    /// The file as we last read or wrote it, to notice edits made behind our back.
+    stamp: Option<FileStamp>,
+    backups: Backups,
+    /// Run `bash -n`/`zsh -n` over generated lines before writing them.
+    syntax_check: bool,
+    /// Why the rc file couldn't be opened; every read and write fails with it.
+    unavailable: Option<String>,
+    /// Set in the generated file mode, where `config_path` is `aliases.sh`.
+    generated: Option<Generated>,
 }
 
 impl AliasStore {
     pub fn new() -> Result<Self, AliasStoreError> {
         let (config_path, source) = Self::detect_shell_config()?;
+        let config = Config::load().map_err(AliasStoreError::ConfigError)?;
+        match config.alias_mode {
+            AliasMode::Rc => Self::open(config_path, source),
+            AliasMode::Generated => {
+                let dir = Config::dir().map_err(AliasStoreError::ConfigError)?;
+                Self::open_generated(&dir, config_path, source)
+            }
+        }
+    }
+
+    /// Keeps the definitions in `<dir>/aliases.sh`, which `rc_path` sources,
+    /// and writes `<dir>/aliases.fish` next to it on every save.
+    pub fn open_generated(
+        dir: &Path,
+        rc_path: PathBuf,
+        source: AliasSource,
+    ) -> Result<Self, AliasStoreError> {
+        let script = dir.join(generated::SCRIPT_FILE);
+        if !script.exists() {
+            fs::create_dir_all(dir).map_err(AliasStoreError::WriteError)?;
+            atomic_write(&script, generated::SCRIPT_HEADER.as_bytes())
+                .map_err(AliasStoreError::WriteError)?;
+        }
+        let mut store = Self::open(script, source)?;
+        store.generated = Some(Generated {
+            rc_path,
+            fish_path: dir.join(generated::FISH_FILE),
+        });
+        Ok(store)
+    }
+
+    pub fn open(config_path: PathBuf, source: AliasSource) -> Result<Self, AliasStoreError> {
+        let backups = Backups::for_file(&config_path).map_err(AliasStoreError::BackupError)?;
         let mut store = Self {
-            aliases: HashMap::new(),
+            definitions: Definitions::default(),
             config_path,
             source,
+            document: RcFile::default(),
+            origins: HashMap::new(),
+            stamp: None,
+            backups,
+            syntax_check: true,
+            unavailable: None,
+            generated: None,
         };
         store.load()?;
         Ok(store)
     }
 
+    /// An empty store standing in for one that failed to open, so the rest
+    /// of the app keeps working.
+    pub fn unavailable(reason: String) -> Self {
+        Self {
+            definitions: Definitions::default(),
+            config_path: PathBuf::new(),
+            source: AliasSource::Bash,
+            document: RcFile::default(),
+            origins: HashMap::new(),
+            stamp: None,
+            backups: Backups::in_dir(PathBuf::new(), Path::new("")),
+            syntax_check: true,
+            unavailable: Some(reason),
+            generated: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_backups(mut self, backups: Backups) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    pub fn set_syntax_check(&mut self, enabled: bool) {
+        self.syntax_check = enabled;
+    }
+
     fn detect_shell_config() -> Result<(PathBuf, AliasSource), AliasStoreError> {
         let home = directories::BaseDirs::new()
             .ok_or(AliasStoreError::ConfigPathNotFound)?
@@ -64,99 +474,422 @@ impl AliasStore {
     }
 
     pub fn load(&mut self) -> Result<(), AliasStoreError> {
-        let file = fs::File::open(&self.config_path).map_err(AliasStoreError::ReadError)?;
-        let reader = BufReader::new(file);
+        if let Some(reason) = &self.unavailable {
+            return Err(AliasStoreError::Unavailable(reason.clone()));
+        }
+        let content = fs::read_to_string(&self.config_path).map_err(AliasStoreError::ReadError)?;
 
-        self.aliases.clear();
+        self.document = RcFile::parse(&content);
+        self.stamp = Some(FileStamp::new(content.as_bytes()));
+        self.definitions = Definitions::default();
+        self.origins.clear();
 
-        for line in reader.lines() {
-            let line = line.map_err(AliasStoreError::ReadError)?;
-            if let Some(alias) = Alias::parse_line(&line) {
-                self.aliases.insert(alias.name.clone(), alias);
-            }
+        for (span, definition) in Self::scan(&self.document, &self.source) {
+            let (id, text, shadowed) = match definition {
+                Definition::Alias(alias) => (
+                    alias.id,
+                    alias.to_alias_string(),
+                    self.definitions
+                        .aliases
+                        .insert(alias.name.clone(), alias)
+                        .map(|a| a.id),
+                ),
+                Definition::Function(function) => (
+                    function.id,
+                    function.to_function_string(),
+                    self.definitions
+                        .functions
+                        .insert(function.name.clone(), function)
+                        .map(|f| f.id),
+                ),
+                Definition::EnvVar(var) => {
+                    let found = (var.id, var.to_export_string(), None);
+                    self.definitions.env_vars.push(var);
+                    found
+                }
+            };
+            // The earlier lines of a redefined name go with the last one, so
+            // an edit or delete doesn't leave them to come back on reload.
+            let mut spans = shadowed
+                .and_then(|id| self.origins.remove(&id))
+                .unwrap_or_default();
+            spans.push((span, text));
+            self.origins.insert(id, spans);
         }
 
         Ok(())
     }
 
-    pub fn save(&self) -> Result<(), AliasStoreError> {
-        let original_content =
-            fs::read_to_string(&self.config_path).map_err(AliasStoreError::ReadError)?;
+    /// Every alias, function and export in `document`, in file order.
+    fn scan(document: &RcFile, source: &AliasSource) -> Vec<(Range<usize>, Definition)> {
+        let lines: Vec<&str> = document.lines().map(|(_, line)| line).collect();
+        let mut found = Vec::new();
+        let mut i = 0;
 
-        let mut new_content = String::new();
-        let mut alias_block_started = false;
-        let mut in_alias_block = false;
-
-        for line in original_content.lines() {
-            if line.contains("# SNIPLIAS ALIASES START") {
-                alias_block_started = true;
-                in_alias_block = true;
+        while i < lines.len() {
+            if let Some((function, len)) = ShellFunction::parse_lines(&lines[i..]) {
+                found.push((i..i + len, Definition::Function(function)));
+                i += len;
                 continue;
             }
-
-            if line.contains("# SNIPLIAS ALIASES END") {
-                in_alias_block = false;
-                continue;
+            if let Some(alias) = Alias::parse_line(lines[i], source.clone()) {
+                found.push((i..i + 1, Definition::Alias(alias)));
+            } else if let Some(var) = EnvVar::parse_line(lines[i]) {
+                found.push((i..i + 1, Definition::EnvVar(var)));
             }
+            i += 1;
+        }
 
-            if in_alias_block {
-                continue;
-            }
+        found
+    }
 
-            if !alias_block_started && line.trim().starts_with("alias ") {
+    /// Line edits that turn the loaded document into `definitions`.
+    ///
+    /// Definitions that were loaded and left alone keep their lines untouched,
+    /// changed ones are rewritten where they stand and only those that were
+    /// never in the file are appended to the managed block. Earlier lines of
+    /// a name defined more than once are removed along with a change to it.
+    fn pending_edits(&self, definitions: &Definitions) -> Vec<RcEdit> {
+        let current = definitions.rendered();
+        let mut edits = Vec::new();
+
+        for (id, spans) in &self.origins {
+            let Some(((span, original), earlier)) = spans.split_last() else {
                 continue;
+            };
+            let text = match current.get(id) {
+                Some((_, text)) if text == original => continue,
+                Some((_, text)) => Some(text),
+                None => None,
+            };
+            edits.extend(
+                earlier
+                    .iter()
+                    .map(|(span, _)| RcEdit::Remove { span: span.clone() }),
+            );
+            edits.push(match text {
+                Some(text) => RcEdit::Replace {
+                    span: span.clone(),
+                    text: text.clone(),
+                },
+                None => RcEdit::Remove { span: span.clone() },
+            });
+        }
+
+        let mut added: Vec<_> = current
+            .iter()
+            .filter(|(id, _)| !self.origins.contains_key(id))
+            .map(|(_, entry)| entry)
+            .collect();
+        added.sort_by(|a, b| a.0.cmp(&b.0));
+        edits.extend(
+            added
+                .into_iter()
+                .map(|(_, text)| RcEdit::Append { text: text.clone() }),
+        );
+
+        edits
+    }
+
+    pub fn save(&mut self) -> Result<(), AliasStoreError> {
+        if let Some(reason) = &self.unavailable {
+            return Err(AliasStoreError::Unavailable(reason.clone()));
+        }
+        let edits = self.pending_edits(&self.definitions);
+        if edits.is_empty() {
+            return Ok(());
+        }
+
+        let on_disk = FileStamp::read(&self.config_path).map_err(AliasStoreError::ReadError)?;
+        if on_disk != self.stamp {
+            return Err(AliasStoreError::Conflict(self.config_path.clone()));
+        }
+
+        if self.syntax_check {
+            self.check_syntax(&edits)?;
+        }
+
+        let new_content = self.document.render(&edits);
+        self.backup_current()?;
+        atomic_write(&self.config_path, new_content.as_bytes())
+            .map_err(AliasStoreError::WriteError)?;
+
+        self.document = RcFile::parse(&new_content);
+        self.stamp = Some(FileStamp::new(new_content.as_bytes()));
+        self.origins.clear();
+        let rendered = self.definitions.rendered();
+        let mut taken = Vec::new();
+        for (span, parsed) in Self::scan(&self.document, &self.source) {
+            if let Some(id) = self.definitions.id_of(&parsed, &taken) {
+                taken.push(id);
+                self.origins
+                    .entry(id)
+                    .or_default()
+                    .push((span, rendered[&id].1.clone()));
             }
+        }
+
+        if let Some(generated) = &self.generated {
+            let fish = generated::render_fish(&self.list(), &self.list_env_vars());
+            atomic_write(&generated.fish_path, fish.as_bytes())
+                .map_err(AliasStoreError::WriteError)?;
+            self.install_source_hooks()?;
+        }
+
+        Ok(())
+    }
 
-            new_content.push_str(line);
-            new_content.push('\n');
+    /// In the generated file mode, adds the line that sources `aliases.sh`
+    /// to the rc file, and `aliases.fish` to fish's config if fish is set
+    /// up. Files that already source them are left alone. Returns the files
+    /// that were changed.
+    pub fn install_source_hooks(&self) -> Result<Vec<PathBuf>, AliasStoreError> {
+        let Some(generated) = &self.generated else {
+            return Ok(Vec::new());
+        };
+        let mut hooks = vec![(
+            generated.rc_path.clone(),
+            &self.config_path,
+            generated::sh_hook(&self.config_path),
+        )];
+        if let Some(fish_config) = generated::fish_config() {
+            hooks.push((
+                fish_config,
+                &generated.fish_path,
+                generated::fish_hook(&generated.fish_path),
+            ));
         }
 
-        if !self.aliases.is_empty() {
-            new_content.push_str("\n# SNIPLIAS ALIASES START\n");
-            for alias in self.aliases.values() {
-                new_content.push_str(&alias.to_alias_string());
-                new_content.push('\n');
+        let mut changed = Vec::new();
+        for (rc, script, hook) in hooks {
+            let installed = generated::install_hook(&rc, script, &hook).map_err(|source| {
+                AliasStoreError::HookError {
+                    path: rc.clone(),
+                    source,
+                }
+            })?;
+            if installed {
+                changed.push(rc);
             }
-            new_content.push_str("# SNIPLIAS ALIASES END\n");
         }
+        Ok(changed)
+    }
 
-        let mut file = fs::File::create(&self.config_path).map_err(AliasStoreError::WriteError)?;
-        file.write_all(new_content.as_bytes())
+    /// Feeds the lines sniplias generated to the shell's parser without
+    /// running them. Skipped if the shell isn't installed.
+    fn check_syntax(&self, edits: &[RcEdit]) -> Result<(), AliasStoreError> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let generated: String = edits
+            .iter()
+            .filter_map(|edit| match edit {
+                RcEdit::Replace { text, .. } | RcEdit::Append { text } => {
+                    Some(format!("{}\n", text))
+                }
+                RcEdit::Remove { .. } => None,
+            })
+            .collect();
+        if generated.is_empty() {
+            return Ok(());
+        }
+
+        let shell = self.source.as_str();
+        let mut child = match Command::new(shell)
+            .arg("-n")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(AliasStoreError::WriteError(e)),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(generated.as_bytes())
+                .map_err(AliasStoreError::WriteError)?;
+        }
+        let output = child
+            .wait_with_output()
             .map_err(AliasStoreError::WriteError)?;
 
-        Ok(())
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(AliasStoreError::SyntaxError {
+                shell: shell.to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+
+    /// Copies the file as it is on disk right now into the backup rotation.
+    fn backup_current(&self) -> Result<(), AliasStoreError> {
+        match fs::read(&self.config_path) {
+            Ok(current) => {
+                self.backups
+                    .create(&current)
+                    .map_err(AliasStoreError::BackupError)?;
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AliasStoreError::ReadError(e)),
+        }
+    }
+
+    pub fn backups(&self) -> &Backups {
+        &self.backups
+    }
+
+    /// Rolls the rc file back to `entry`. The current file is backed up first,
+    /// so a restore can itself be undone.
+    pub fn restore(&mut self, entry: &BackupEntry) -> Result<(), AliasStoreError> {
+        let contents = fs::read(&entry.path).map_err(AliasStoreError::ReadError)?;
+        self.backup_current()?;
+        atomic_write(&self.config_path, &contents).map_err(AliasStoreError::WriteError)?;
+        self.load()
+    }
+
+    pub fn config_path(&self) -> &PathBuf {
+        &self.config_path
     }
 
     pub fn list(&self) -> Vec<&Alias> {
-        self.aliases.values().collect()
+        self.definitions.aliases.values().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Alias> {
+        self.definitions.aliases.get(name)
     }
 
     pub fn list_filtered(&self, query: &str) -> Vec<&Alias> {
-        self.aliases
+        self.definitions
+            .aliases
             .values()
             .filter(|a| a.matches_search(query))
             .collect()
     }
 
+    pub fn list_functions(&self) -> Vec<&ShellFunction> {
+        self.definitions.functions.values().collect()
+    }
+
+    #[cfg(test)]
+    pub fn get_function(&self, name: &str) -> Option<&ShellFunction> {
+        self.definitions.functions.get(name)
+    }
+
+    /// Every export, in file order. A name exported more than once is
+    /// listed each time.
+    pub fn list_env_vars(&self) -> Vec<&EnvVar> {
+        self.definitions.env_vars.iter().collect()
+    }
+
+    /// The export in effect for `name`, i.e. the last one.
+    #[cfg(test)]
+    pub fn get_env_var(&self, name: &str) -> Option<&EnvVar> {
+        self.definitions
+            .env_vars
+            .iter()
+            .rev()
+            .find(|v| v.name == name)
+    }
+
+    pub fn list_env_vars_filtered(&self, query: &str) -> Vec<&EnvVar> {
+        self.definitions
+            .env_vars
+            .iter()
+            .filter(|v| v.matches_search(query))
+            .collect()
+    }
+
+    pub fn list_functions_filtered(&self, query: &str) -> Vec<&ShellFunction> {
+        self.definitions
+            .functions
+            .values()
+            .filter(|f| f.matches_search(query))
+            .collect()
+    }
+
+    /// Applies `change` and saves. If the save fails the in-memory set is
+    /// left as it was, so the caller can reload, merge or give up.
+    pub fn apply(&mut self, change: AliasChange) -> Result<(), AliasStoreError> {
+        let snapshot = self.definitions.clone();
+        let result = self.definitions.apply(change).and_then(|_| self.save());
+        if result.is_err() {
+            self.definitions = snapshot;
+        }
+        result
+    }
+
+    /// The change that would take back `change`, worked out before it is
+    /// applied. None if it names something that doesn't exist.
+    pub fn inverse(&self, change: &AliasChange) -> Option<AliasChange> {
+        self.definitions.inverse(change)
+    }
+
+    /// Reloads the file and replays `change` on top of whatever is there now.
+    pub fn merge(&mut self, change: AliasChange) -> Result<(), AliasStoreError> {
+        match self.reload_and_rebase(change)? {
+            Some(change) => self.apply(change),
+            None => Ok(()),
+        }
+    }
+
+    /// Reloads the file and adjusts `change` to what is there now, without
+    /// applying it. None if nothing of it is left to make.
+    pub fn reload_and_rebase(
+        &mut self,
+        change: AliasChange,
+    ) -> Result<Option<AliasChange>, AliasStoreError> {
+        self.load()?;
+        Ok(self.definitions.rebase(change))
+    }
+
+    /// A unified diff of the rc file as `change` would leave it, without
+    /// writing anything. Empty if the change wouldn't alter the file.
+    pub fn preview(&self, change: &AliasChange) -> Result<String, AliasStoreError> {
+        if let Some(reason) = &self.unavailable {
+            return Err(AliasStoreError::Unavailable(reason.clone()));
+        }
+        let mut definitions = self.definitions.clone();
+        definitions.apply(change.clone())?;
+
+        let before = self.document.render(&[]);
+        let after = self.document.render(&self.pending_edits(&definitions));
+        let label = self.config_path.display().to_string();
+        // Credentials stay hidden here, as they are in the list. Each line
+        // starts with the diff's one-character mark.
+        let diff = unified_diff(&before, &after, &label, &label)
+            .lines()
+            .map(
+                |line| match EnvVar::mask_line(line.get(1..).unwrap_or("")) {
+                    Some(masked) => format!("{}{}\n", &line[..1], masked),
+                    None => format!("{}\n", line),
+                },
+            )
+            .collect();
+        Ok(diff)
+    }
+
+    #[cfg(test)]
     pub fn add(&mut self, alias: Alias) -> Result<(), AliasStoreError> {
-        self.aliases.insert(alias.name.clone(), alias);
-        self.save()
+        self.apply(AliasChange::Add(alias))
     }
 
+    #[cfg(test)]
     pub fn update(&mut self, name: &str, new_alias: Alias) -> Result<(), AliasStoreError> {
-        if !self.aliases.contains_key(name) {
-            return Err(AliasStoreError::AliasNotFound(name.to_string()));
-        }
-        self.aliases.remove(name);
-        self.aliases.insert(new_alias.name.clone(), new_alias);
-        self.save()
+        self.apply(AliasChange::Update {
+            name: name.to_string(),
+            alias: new_alias,
+        })
     }
 
+    #[cfg(test)]
     pub fn delete(&mut self, name: &str) -> Result<(), AliasStoreError> {
-        if self.aliases.remove(name).is_none() {
-            return Err(AliasStoreError::AliasNotFound(name.to_string()));
-        }
-        self.save()
+        self.apply(AliasChange::Delete(name.to_string()))
     }
 
     pub fn source(&self) -> &AliasSource {
@@ -164,6 +897,9 @@ impl AliasStore {
     }
 
     pub fn source_command(&self) -> Option<String> {
+        if self.unavailable.is_some() {
+            return None;
+        }
         let path = self.config_path.to_string_lossy();
         Some(format!("source {}", path))
     }
@@ -174,3 +910,261 @@ impl Default for AliasStore {
         Self::new().expect("Failed to initialize AliasStore")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with(content: &str) -> (tempfile::TempDir, AliasStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".bashrc");
+        fs::write(&path, content).unwrap();
+        let backups = Backups::in_dir(dir.path().join("backups"), &path);
+        let store = AliasStore::open(path, AliasSource::Bash)
+            .unwrap()
+            .with_backups(backups);
+        (dir, store)
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_not_written() {
+        let content = "alias gs='git status'\n";
+        let (_dir, mut store) = store_with(content);
+
+        let bad_name = Alias::new("g s".into(), "git status".into(), AliasSource::Bash);
+        assert!(matches!(
+            store.add(bad_name),
+            Err(AliasStoreError::InvalidAlias(_))
+        ));
+
+        assert!(store.get("g s").is_none());
+
+        for name in ["my func", "f(", "a;rm", ""] {
+            let function = ShellFunction::new(name.into(), "true".into(), AliasSource::Bash);
+            assert!(matches!(
+                store.apply(AliasChange::AddFunction(function)),
+                Err(AliasStoreError::InvalidAlias(_))
+            ));
+        }
+        assert_eq!(fs::read_to_string(&store.config_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_syntax_errors_are_not_written() {
+        if std::process::Command::new("bash")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+        let content = "alias gs='git status'\n";
+        let (_dir, mut store) = store_with(content);
+
+        let broken = ShellFunction::new("f".into(), "if true; then".into(), AliasSource::Bash);
+        assert!(matches!(
+            store.apply(AliasChange::AddFunction(broken)),
+            Err(AliasStoreError::SyntaxError { .. })
+        ));
+
+        assert!(store.get_function("f").is_none());
+        assert_eq!(fs::read_to_string(&store.config_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_redefined_alias_is_edited_and_deleted_everywhere() {
+        let content = "alias gs='git status'\nexport EDITOR=vim\nalias gs='git status -sb'\n";
+        let (_dir, mut store) = store_with(content);
+        assert_eq!(store.get("gs").unwrap().command, "git status -sb");
+
+        let mut edited = store.get("gs").unwrap().clone();
+        edited.command = "git status -s".to_string();
+        store.update("gs", edited).unwrap();
+        let saved = fs::read_to_string(&store.config_path).unwrap();
+        assert_eq!(saved.matches("alias gs=").count(), 1, "{}", saved);
+        assert!(saved.contains("git status -s'"));
+
+        store.delete("gs").unwrap();
+        store.load().unwrap();
+        assert!(store.get("gs").is_none());
+    }
+
+    #[test]
+    fn test_aliases_take_the_shell_of_their_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".zshrc");
+        fs::write(&path, "alias a/b='true'\nalias -g G='| grep'\n").unwrap();
+        let mut store = AliasStore::open(path, AliasSource::Zsh).unwrap();
+        assert_eq!(store.get("a/b").unwrap().source_file, AliasSource::Zsh);
+        let mut edited = store.get("a/b").unwrap().clone();
+        edited.command = "false".to_string();
+        store.update("a/b", edited).unwrap();
+
+        let (_dir, mut store) = store_with("alias -g G='| grep'\n");
+        let mut edited = store.get("G").unwrap().clone();
+        edited.command = "| grep -i".to_string();
+        assert!(matches!(
+            store.update("G", edited),
+            Err(AliasStoreError::InvalidAlias(_))
+        ));
+    }
+
+    #[test]
+    fn test_undoing_a_re_add_puts_the_old_alias_back() {
+        let content = "alias gs='git status'\n";
+        let (_dir, mut store) = store_with(content);
+        let gs = Alias::new("gs".into(), "git status -sb".into(), AliasSource::Bash);
+        let ll = Alias::new("ll".into(), "ls -l".into(), AliasSource::Bash);
+        let change = AliasChange::Batch(vec![AliasChange::Add(ll), AliasChange::Add(gs)]);
+
+        let undo = store.inverse(&change).unwrap();
+        store.apply(change).unwrap();
+        store.apply(undo).unwrap();
+        store.load().unwrap();
+        assert_eq!(store.get("gs").unwrap().command, "git status");
+        assert!(store.get("ll").is_none());
+    }
+
+    #[test]
+    fn test_each_export_is_listed_and_edited_on_its_own() {
+        let content = "export PATH=\"$HOME/bin:$PATH\"\nexport EDITOR=vim\nexport PATH=\"$HOME/.cargo/bin:$PATH\"\n";
+        let (_dir, mut store) = store_with(content);
+        let paths: Vec<_> = store
+            .list_env_vars()
+            .into_iter()
+            .filter(|v| v.name == "PATH")
+            .cloned()
+            .collect();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(store.get_env_var("PATH").unwrap().value, paths[1].value);
+
+        let mut var = paths[0].clone();
+        var.set_entries(&["$HOME/.local/bin", "$PATH"]);
+        let change = AliasChange::UpdateEnv {
+            old: paths[0].clone(),
+            var,
+        };
+        let undo = store.inverse(&change).unwrap();
+        store.apply(change).unwrap();
+        let saved = fs::read_to_string(&store.config_path).unwrap();
+        assert!(saved.starts_with("export PATH=\"$HOME/.local/bin:$PATH\""));
+        assert!(saved.ends_with("export PATH=\"$HOME/.cargo/bin:$PATH\"\n"));
+
+        store.apply(undo).unwrap();
+        store
+            .apply(AliasChange::DeleteEnv(paths[1].clone()))
+            .unwrap();
+        store.load().unwrap();
+        let values: Vec<_> = store.list_env_vars().iter().map(|v| &v.value).collect();
+        assert_eq!(values, ["$HOME/bin:$PATH", "vim"]);
+
+        let token = EnvVar::new(
+            "GITHUB_TOKEN".into(),
+            "ghp_secret".into(),
+            AliasSource::Bash,
+        );
+        let diff = store.preview(&AliasChange::AddEnv(token)).unwrap();
+        assert!(diff.contains("+export GITHUB_TOKEN=••••••••"), "{}", diff);
+        assert!(!diff.contains("ghp_secret"));
+
+        let bad = EnvVar::new("MY-VAR".into(), "x".into(), AliasSource::Bash);
+        assert!(matches!(
+            store.apply(AliasChange::AddEnv(bad)),
+            Err(AliasStoreError::InvalidAlias(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_rewrites_only_changed_lines() {
+        let content = "# git\nalias gs='git status'\nif [ -x /usr/bin/exa ]; then\n  alias ls='exa'\nfi\nalias ll='ls -la'\n";
+        let (_dir, mut store) = store_with(content);
+
+        let ls = Alias::new("ls".into(), "exa --icons".into(), AliasSource::Bash);
+        store.update("ls", ls).unwrap();
+        store.delete("ll").unwrap();
+        store
+            .add(Alias::new(
+                "gp".into(),
+                "git push".into(),
+                AliasSource::Bash,
+            ))
+            .unwrap();
+
+        let saved = fs::read_to_string(&store.config_path).unwrap();
+        assert_eq!(
+            saved,
+            format!(
+                "# git\nalias gs='git status'\nif [ -x /usr/bin/exa ]; then\n  {}\nfi\n\n# SNIPLIAS ALIASES START\n{}\n# SNIPLIAS ALIASES END\n",
+                store.get("ls").unwrap().to_alias_string(),
+                store.get("gp").unwrap().to_alias_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_function_is_rewritten_in_place() {
+        let content = "mkcd() {\n  mkdir -p \"$1\" && cd \"$1\"\n}\nalias gs='git status'\n";
+        let (_dir, mut store) = store_with(content);
+        assert_eq!(store.list_functions().len(), 1);
+        assert_eq!(store.list().len(), 1);
+
+        let function = ShellFunction::new(
+            "mkcd".into(),
+            "mkdir -p \"$1\"\ncd \"$1\"".into(),
+            AliasSource::Bash,
+        );
+        let change = AliasChange::UpdateFunction {
+            name: "mkcd".into(),
+            function,
+        };
+        store.apply(change).unwrap();
+
+        let saved = fs::read_to_string(&store.config_path).unwrap();
+        assert_eq!(
+            saved,
+            format!(
+                "{}\nalias gs='git status'\n",
+                store.get_function("mkcd").unwrap().to_function_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_restore_rolls_back_and_backs_up_current() {
+        let (_dir, mut store) = store_with("alias gs='git status'\n");
+        store.delete("gs").unwrap();
+
+        let backups = store.backups().list().unwrap();
+        assert_eq!(backups.len(), 1);
+        store.restore(&backups[0]).unwrap();
+
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(store.backups().list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_external_edit_is_detected_and_merged() {
+        let (_dir, mut store) = store_with("alias gs='git status'\n");
+        fs::write(
+            &store.config_path,
+            "alias gs='git status'\nalias gd='git diff'\n",
+        )
+        .unwrap();
+
+        let change = AliasChange::Add(Alias::new(
+            "gp".into(),
+            "git push".into(),
+            AliasSource::Bash,
+        ));
+        assert!(matches!(
+            store.apply(change.clone()),
+            Err(AliasStoreError::Conflict(_))
+        ));
+        assert_eq!(store.list().len(), 1);
+
+        store.merge(change).unwrap();
+        let mut names: Vec<_> = store.list().iter().map(|a| a.name.clone()).collect();
+        names.sort();
+        assert_eq!(names, ["gd", "gp", "gs"]);
+    }
+}
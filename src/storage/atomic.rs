@@ -0,0 +1,120 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replaces the contents of `path` without ever leaving it half-written.
+///
+/// The data goes to a temporary file next to the real target, is flushed to
+/// disk and then renamed over it. Symlinks are followed so the link itself
+/// survives. The temporary file gets the target's permissions before anything
+/// is written to it, or owner-only ones for a new file, so secrets in an rc
+/// file are never readable by others, not even briefly.
+pub fn atomic_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let target = resolve_target(path)?;
+    let dir = target
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+
+    let tmp_path = dir.join(format!(
+        ".{}.sniplias-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| {
+        let mut tmp = create_private(&tmp_path, &target)?;
+        tmp.write_all(contents)?;
+        tmp.sync_all()?;
+
+        fs::rename(&tmp_path, &target)?;
+
+        // Persist the rename itself; not every platform lets us open a directory.
+        if let Ok(dir) = fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Opens `tmp_path` for writing with the permissions of `target`, or 0600
+/// if it doesn't exist yet.
+fn create_private(tmp_path: &Path, target: &Path) -> io::Result<fs::File> {
+    let existing = fs::metadata(target).ok().map(|m| m.permissions());
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(existing.as_ref().map_or(0o600, |p| p.mode() & 0o7777));
+    }
+    let tmp = options.open(tmp_path)?;
+    // A leftover temporary file keeps its old mode, and umask may have
+    // narrowed the new one; set it exactly before writing.
+    match existing {
+        Some(permissions) => tmp.set_permissions(permissions)?,
+        #[cfg(unix)]
+        None => {
+            use std::os::unix::fs::PermissionsExt;
+            tmp.set_permissions(fs::Permissions::from_mode(0o600))?
+        }
+        #[cfg(not(unix))]
+        None => {}
+    }
+    Ok(tmp)
+}
+
+/// The file that actually holds the data behind `path`, following symlinks.
+fn resolve_target(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(target) => Ok(target),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => match fs::read_link(path) {
+            // A dangling symlink: write where it points so the link comes alive.
+            Ok(link) => Ok(path.parent().map(|p| p.join(&link)).unwrap_or(link)),
+            Err(_) => Ok(path.to_path_buf()),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_write_keeps_symlink_and_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("dotfiles_bashrc");
+        let link = dir.path().join(".bashrc");
+        fs::write(&real, "old\n").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&real, &link).unwrap();
+
+        atomic_write(&link, b"new\n").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new\n");
+        let mode = fs::metadata(&real).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // New files, backups among them, are only readable by their owner.
+        let new = dir.path().join("backup");
+        atomic_write(&new, b"secret\n").unwrap();
+        let mode = fs::metadata(&new).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3f";
+const DEFAULT_KEEP: usize = 10;
+
+/// A rotating set of timestamped copies of one file.
+///
+/// Backups live in `<data dir>/backups/<file name>/` so they survive the
+/// original being rewritten, and only the newest `keep` copies are retained.
+#[derive(Clone)]
+pub struct Backups {
+    dir: PathBuf,
+    keep: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Backups {
+    pub fn for_file(target: &Path) -> io::Result<Self> {
+        Ok(Self::in_dir(backups_root()?, target))
+    }
+
+    /// Backups of a snippet library. Several libraries may share a file name,
+    /// so each gets its own directory under `backups/libraries/`.
+    pub fn for_library(name: &str, target: &Path) -> io::Result<Self> {
+        Ok(Self::in_dir(
+            backups_root()?.join("libraries").join(name),
+            target,
+        ))
+    }
+
+    pub fn in_dir(root: PathBuf, target: &Path) -> Self {
+        let name = target
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unnamed".to_string());
+        Self {
+            dir: root.join(name),
+            keep: DEFAULT_KEEP,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Saves `contents` as a new backup, readable only by its owner, and
+    /// prunes the oldest ones.
+    pub fn create(&self, contents: &[u8]) -> io::Result<BackupEntry> {
+        fs::create_dir_all(&self.dir)?;
+
+        let mut created_at = Utc::now();
+        let mut path = self
+            .dir
+            .join(created_at.format(TIMESTAMP_FORMAT).to_string());
+        // Two saves within the same millisecond must not overwrite each other.
+        while path.exists() {
+            created_at += chrono::Duration::milliseconds(1);
+            path = self
+                .dir
+                .join(created_at.format(TIMESTAMP_FORMAT).to_string());
+        }
+
+        super::atomic_write(&path, contents)?;
+        self.prune()?;
+
+        Ok(BackupEntry { path, created_at })
+    }
+
+    /// All backups, newest first.
+    pub fn list(&self) -> io::Result<Vec<BackupEntry>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut backups: Vec<BackupEntry> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let created_at = NaiveDateTime::parse_from_str(&name, TIMESTAMP_FORMAT).ok()?;
+                Some(BackupEntry {
+                    path: entry.path(),
+                    created_at: Utc.from_utc_datetime(&created_at),
+                })
+            })
+            .collect();
+
+        backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+        Ok(backups)
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        for stale in self.list()?.into_iter().skip(self.keep) {
+            fs::remove_file(stale.path)?;
+        }
+        Ok(())
+    }
+}
+
+fn backups_root() -> io::Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "sniplias", "sniplias")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not find data directory"))?;
+    Ok(dirs.data_dir().join("backups"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = Backups::in_dir(dir.path().to_path_buf(), Path::new("/home/u/.bashrc"));
+
+        for i in 0..DEFAULT_KEEP + 3 {
+            backups.create(format!("v{}", i).as_bytes()).unwrap();
+        }
+
+        let list = backups.list().unwrap();
+        assert_eq!(list.len(), DEFAULT_KEEP);
+        let newest = fs::read_to_string(&list[0].path).unwrap();
+        assert_eq!(newest, format!("v{}", DEFAULT_KEEP + 2));
+    }
+}
@@ -0,0 +1,123 @@
+use super::atomic_write;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// User settings kept in `<config dir>/config.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Where aliases are written.
+    #[serde(default, skip_serializing_if = "AliasMode::is_rc")]
+    pub alias_mode: AliasMode,
+    /// Libraries, by name, committed to their git working tree on every save.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub synced: Vec<String>,
+    /// Snippet libraries registered by the user, besides the global one.
+    #[serde(default)]
+    pub libraries: Vec<LibraryConfig>,
+}
+
+/// How sniplias writes aliases, functions and exports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AliasMode {
+    /// Straight into the shell's rc file, in a managed block.
+    #[default]
+    Rc,
+    /// Into `aliases.sh` and `aliases.fish` next to this file, which the rc
+    /// files source.
+    Generated,
+}
+
+impl AliasMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AliasMode::Rc => "rc",
+            AliasMode::Generated => "generated",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rc" => Some(AliasMode::Rc),
+            "generated" => Some(AliasMode::Generated),
+            _ => None,
+        }
+    }
+
+    fn is_rc(&self) -> bool {
+        *self == AliasMode::Rc
+    }
+}
+
+/// A registered snippet library, such as a team checkout or a vendor pack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryConfig {
+    pub name: String,
+    /// May start with `~/`.
+    pub path: PathBuf,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl LibraryConfig {
+    /// `path` with a leading `~/` expanded to the home directory.
+    pub fn resolved_path(&self) -> PathBuf {
+        expand_home(&self.path)
+    }
+}
+
+impl Config {
+    /// Where the generated alias files live.
+    pub fn dir() -> io::Result<PathBuf> {
+        let path = Self::path()?;
+        Ok(path.parent().map(Path::to_path_buf).unwrap_or_default())
+    }
+
+    pub fn path() -> io::Result<PathBuf> {
+        let dirs =
+            directories::ProjectDirs::from("com", "sniplias", "sniplias").ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Could not find config directory")
+            })?;
+        Ok(dirs.config_dir().join("config.toml"))
+    }
+
+    /// Reads the config file, or the defaults if there isn't one.
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(&Self::path()?)
+    }
+
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        atomic_write(&path, content.as_bytes())
+    }
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (
+        path.strip_prefix("~"),
+        directories::BaseDirs::new().map(|d| d.home_dir().to_path_buf()),
+    ) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
@@ -0,0 +1,93 @@
+use super::atomic_write;
+use crate::models::Alias;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Aliases the user chose to see in sniplias without managing them, such as
+/// ones from oh-my-zsh plugins. They are listed read-only and never written
+/// to the rc file.
+pub struct ExternalAliases {
+    path: PathBuf,
+    aliases: BTreeMap<String, Alias>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ExternalAliasesData {
+    aliases: Vec<Alias>,
+}
+
+impl ExternalAliases {
+    pub fn open() -> io::Result<Self> {
+        let dirs =
+            directories::ProjectDirs::from("com", "sniplias", "sniplias").ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Could not find data directory")
+            })?;
+        Self::in_file(dirs.data_dir().join("external_aliases.json"))
+    }
+
+    pub fn in_file(path: PathBuf) -> io::Result<Self> {
+        let data: ExternalAliasesData = match fs::read_to_string(&path) {
+            Ok(content) if !content.trim().is_empty() => serde_json::from_str(&content)?,
+            Ok(_) => ExternalAliasesData::default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => ExternalAliasesData::default(),
+            Err(e) => return Err(e),
+        };
+        let aliases = data
+            .aliases
+            .into_iter()
+            .map(|mut a| {
+                a.external = true;
+                (a.name.clone(), a)
+            })
+            .collect();
+        Ok(Self { path, aliases })
+    }
+
+    /// An empty list that is never written, for when the data directory
+    /// can't be used.
+    pub fn in_memory() -> Self {
+        Self {
+            path: PathBuf::new(),
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    pub fn list(&self) -> Vec<&Alias> {
+        self.aliases.values().collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.aliases.contains_key(name)
+    }
+
+    pub fn mark(&mut self, aliases: Vec<Alias>) -> io::Result<()> {
+        for mut alias in aliases {
+            alias.external = true;
+            self.aliases.insert(alias.name.clone(), alias);
+        }
+        self.save()
+    }
+
+    pub fn forget(&mut self, name: &str) -> io::Result<()> {
+        if self.aliases.remove(name).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if self.path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        let data = ExternalAliasesData {
+            aliases: self.aliases.values().cloned().collect(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        atomic_write(&self.path, serde_json::to_string_pretty(&data)?.as_bytes())
+    }
+}
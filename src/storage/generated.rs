@@ -0,0 +1,196 @@
+//! The "generated file" mode: aliases live in files sniplias owns, and the
+//! shell's rc file sources them with a single line.
+
+use super::{atomic_write, Backups};
+use crate::models::{Alias, AliasKind, EnvVar};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Sourced by bash and zsh; edited like an rc file.
+pub const SCRIPT_FILE: &str = "aliases.sh";
+/// Rewritten from the definitions on every save.
+pub const FISH_FILE: &str = "aliases.fish";
+
+pub const SCRIPT_HEADER: &str = "# Aliases managed by sniplias, sourced from your shell config.\n";
+
+/// `~/.config/fish/config.fish`, if fish has a config directory.
+pub fn fish_config() -> Option<PathBuf> {
+    let dir = directories::BaseDirs::new()?.config_dir().join("fish");
+    dir.is_dir().then(|| dir.join("config.fish"))
+}
+
+/// The line that loads `script` from a bash or zsh rc file.
+pub fn sh_hook(script: &Path) -> String {
+    let path = quote(script);
+    format!("[ -f {path} ] && . {path}  # sniplias")
+}
+
+/// The line that loads `script` from fish's `config.fish`.
+pub fn fish_hook(script: &Path) -> String {
+    let path = quote(script);
+    format!("test -f {path}; and source {path}  # sniplias")
+}
+
+/// Appends `hook` to `rc` unless it already mentions `script`, whether
+/// through an earlier hook or a `source` line of the user's own. The file is
+/// backed up first. Returns whether anything was written.
+pub fn install_hook(rc: &Path, script: &Path, hook: &str) -> io::Result<bool> {
+    let content = match fs::read_to_string(rc) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    if content.contains(&*script.to_string_lossy()) {
+        return Ok(false);
+    }
+
+    if !content.is_empty() {
+        Backups::for_file(rc)?.create(content.as_bytes())?;
+    }
+    let mut updated = content;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(hook);
+    updated.push('\n');
+    if let Some(parent) = rc.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    atomic_write(rc, updated.as_bytes())?;
+    Ok(true)
+}
+
+/// The fish version of the definitions. Functions, zsh's global and suffix
+/// aliases, and exports using shell syntax fish lacks have no fish
+/// equivalent and are left out.
+pub fn render_fish(aliases: &[&Alias], env_vars: &[&EnvVar]) -> String {
+    let mut aliases: Vec<&Alias> = aliases
+        .iter()
+        .copied()
+        .filter(|a| a.kind == AliasKind::Regular)
+        .collect();
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out =
+        String::from("# Generated by sniplias from aliases.sh; changes are overwritten.\n");
+    for var in env_vars {
+        match fish_value(&var.value) {
+            Some(value) => out.push_str(&format!("set -gx {} {}\n", var.name, value)),
+            None => out.push_str(&format!(
+                "# {} skipped: fish can't expand its value\n",
+                var.name
+            )),
+        }
+    }
+    for alias in aliases {
+        out.push_str(&format!(
+            "alias {} {}\n",
+            alias.name,
+            fish_quote(&alias.command)
+        ));
+    }
+    out
+}
+
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// `value`, with its `$NAME` and `${NAME}` references, as a fish
+/// double-quoted string. Double quotes keep `PATH`-style lists joined with
+/// `:`. None if it uses anything else, e.g. `$(...)` or `${NAME:-default}`.
+fn fish_value(value: &str) -> Option<String> {
+    let is_name = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+    let mut out = String::from("\"");
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => {
+                let braced = chars.next_if_eq(&'{').is_some();
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(is_name) {
+                    name.push(c);
+                }
+                if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+                    return None;
+                }
+                if braced && chars.next_if_eq(&'}').is_none() {
+                    return None;
+                }
+                out.push('$');
+                out.push_str(&name);
+                // fish has no `${NAME}`; ending the quotes ends the name.
+                if chars.peek().is_some_and(is_name) {
+                    out.push_str("\"\"");
+                }
+            }
+            '`' => return None,
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    Some(out)
+}
+
+/// fish single quotes only know `\'` and `\\`.
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AliasSource;
+
+    #[test]
+    fn test_install_hook_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let rc = dir.path().join(".bashrc");
+        let script = dir.path().join("aliases.sh");
+        fs::write(&rc, "export EDITOR=vim").unwrap();
+
+        assert!(install_hook(&rc, &script, &sh_hook(&script)).unwrap());
+        assert!(!install_hook(&rc, &script, &sh_hook(&script)).unwrap());
+        let content = fs::read_to_string(&rc).unwrap();
+        assert_eq!(content.matches("# sniplias").count(), 1);
+        assert!(content.starts_with("export EDITOR=vim\n[ -f '"));
+    }
+
+    #[test]
+    fn test_render_fish() {
+        let gs = Alias::new("gs".into(), "git status".into(), AliasSource::Zsh);
+        let quoted = Alias::new("hi".into(), r"echo 'a\b'".into(), AliasSource::Zsh);
+        let global =
+            Alias::new("G".into(), "| grep".into(), AliasSource::Zsh).with_kind(AliasKind::Global);
+        let var = EnvVar::new("EDITOR".into(), "vim".into(), AliasSource::Zsh);
+
+        let fish = render_fish(&[&quoted, &global, &gs], &[&var]);
+        assert!(fish.ends_with(
+            "set -gx EDITOR \"vim\"\nalias gs 'git status'\nalias hi 'echo \\'a\\\\b\\''\n"
+        ));
+    }
+
+    #[test]
+    fn test_fish_values() {
+        assert_eq!(
+            fish_value("$HOME/bin:${GOPATH}/bin:$PATH").unwrap(),
+            r#""$HOME/bin:$GOPATH/bin:$PATH""#
+        );
+        assert_eq!(fish_value("${USER}_dev").unwrap(), r#""$USER""_dev""#);
+        assert_eq!(
+            fish_value(r#"say "hi" \o/"#).unwrap(),
+            r#""say \"hi\" \\o/""#
+        );
+        assert!(fish_value("$(date +%F)").is_none());
+        assert!(fish_value("${EDITOR:-vim}").is_none());
+        assert!(fish_value("`hostname`").is_none());
+
+        let var = EnvVar::new("TODAY".into(), "$(date)".into(), AliasSource::Zsh);
+        assert!(render_fish(&[], &[&var]).contains("# TODAY skipped"));
+    }
+}
@@ -0,0 +1,259 @@
+//! Commands from the shells' history files, ranked by how often they were
+//! run, for turning into snippets.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// A distinct command from the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub command: String,
+    /// How many times it was run.
+    pub count: usize,
+    /// Position of the latest run; higher is more recent.
+    last_seen: usize,
+}
+
+/// Every command in `~/.bash_history`, `~/.zsh_history` and fish's history,
+/// most frequent first. Missing or unreadable files are skipped.
+pub fn load_history() -> Vec<HistoryEntry> {
+    let Some(dirs) = directories::BaseDirs::new() else {
+        return Vec::new();
+    };
+    let read_bytes = |path: PathBuf| fs::read(path).unwrap_or_default();
+    let read = |path: PathBuf| String::from_utf8_lossy(&read_bytes(path)).into_owned();
+
+    let mut commands = parse_bash(&read(dirs.home_dir().join(".bash_history")));
+    let zsh = unmetafy(&read_bytes(dirs.home_dir().join(".zsh_history")));
+    commands.extend(parse_zsh(&String::from_utf8_lossy(&zsh)));
+    commands.extend(parse_fish(&read(
+        dirs.data_dir().join("fish").join("fish_history"),
+    )));
+    rank(commands)
+}
+
+/// Dedupes `commands`, in the order they were run, and sorts them by count,
+/// then by how recently they were last run.
+pub fn rank(commands: Vec<String>) -> Vec<HistoryEntry> {
+    let mut entries: HashMap<String, HistoryEntry> = HashMap::new();
+    for (i, command) in commands.into_iter().enumerate() {
+        let command = command.trim().to_string();
+        if command.is_empty() {
+            continue;
+        }
+        let entry = entries
+            .entry(command.clone())
+            .or_insert_with(|| HistoryEntry {
+                command,
+                count: 0,
+                last_seen: 0,
+            });
+        entry.count += 1;
+        entry.last_seen = i;
+    }
+
+    let mut ranked: Vec<HistoryEntry> = entries.into_values().collect();
+    ranked.sort_by(|a, b| b.count.cmp(&a.count).then(b.last_seen.cmp(&a.last_seen)));
+    ranked
+}
+
+/// One command per line; `#1700000000` lines are timestamps written when
+/// `HISTTIMEFORMAT` is set.
+fn parse_bash(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| {
+            !line
+                .strip_prefix('#')
+                .is_some_and(|ts| !ts.is_empty() && ts.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// zsh writes NUL and bytes 0x83 to 0xA2, which include many of UTF-8's
+/// continuation bytes, as 0x83 followed by the byte XOR 0x20.
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    const META: u8 = 0x83;
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        match b {
+            META => out.extend(iter.next().map(|b| b ^ 0x20)),
+            b => out.push(b),
+        }
+    }
+    out
+}
+
+/// Plain lines, or `: <start>:<duration>;<command>` with `EXTENDED_HISTORY`.
+/// Multi-line commands continue on lines ending in a backslash.
+fn parse_zsh(content: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        let line = match current.take() {
+            Some(mut command) => {
+                command.push('\n');
+                command.push_str(line);
+                command
+            }
+            None => match line.strip_prefix(": ").and_then(|l| l.split_once(';')) {
+                Some((_, command)) => command.to_string(),
+                None => line.to_string(),
+            },
+        };
+        match line.strip_suffix('\\') {
+            Some(continued) => current = Some(continued.to_string()),
+            None => commands.push(line),
+        }
+    }
+    commands.extend(current);
+    commands
+}
+
+/// fish writes `- cmd: <command>` entries with `\n` and `\\` escaped.
+fn parse_fish(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("- cmd: "))
+        .map(|command| {
+            let mut out = String::with_capacity(command.len());
+            let mut chars = command.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some('n')) => {
+                        out.push('\n');
+                        chars.next();
+                    }
+                    ('\\', Some('\\')) => {
+                        out.push('\\');
+                        chars.next();
+                    }
+                    _ => out.push(c),
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// `command` with the words that vary between it and its siblings in the
+/// history turned into `{{variables}}` that default to this command's
+/// values. Siblings run the same program with the same number of words and
+/// differ in at most half the arguments. `None` if nothing varies, or the
+/// command has quotes or braces that word splitting would mangle.
+pub fn suggest_variables(command: &str, history: &[HistoryEntry]) -> Option<String> {
+    if command.contains(['\'', '"', '\n', '{', '}', '\\']) {
+        return None;
+    }
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if words.len() < 2 {
+        return None;
+    }
+
+    let mut varying = vec![false; words.len()];
+    for other in history {
+        let other: Vec<&str> = other.command.split_whitespace().collect();
+        if other.len() != words.len() || other[0] != words[0] {
+            continue;
+        }
+        let differing: Vec<usize> = (1..words.len()).filter(|&i| words[i] != other[i]).collect();
+        if differing.is_empty() || differing.len() * 2 > words.len() - 1 {
+            continue;
+        }
+        for i in differing {
+            if !words[i].starts_with('-') && !other[i].starts_with('-') {
+                varying[i] = true;
+            }
+        }
+    }
+    if !varying.contains(&true) {
+        return None;
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    let mut positional = 0;
+    let suggested: Vec<String> = words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            if !varying[i] {
+                return word.to_string();
+            }
+            // `-n prod` becomes `{{n:prod}}`, bare arguments `{{arg1:...}}`.
+            let flag: String = words[i - 1]
+                .trim_start_matches('-')
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            let mut name = if words[i - 1].starts_with('-') && !flag.is_empty() {
+                flag
+            } else {
+                positional += 1;
+                format!("arg{}", positional)
+            };
+            if names.contains(&name) {
+                name = format!("{}{}", name, i);
+            }
+            names.push(name.clone());
+            format!("{{{{{}:{}}}}}", name, word)
+        })
+        .collect();
+    Some(suggested.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_rank_history() {
+        let mut commands = parse_bash("#1700000000\ngit status\nls\ngit status\n");
+        commands.extend(parse_zsh(
+            ": 1700000001:0;git status\n: 1700000002:3;for f in *; do\\\n  echo $f\\\ndone\nls\n",
+        ));
+        commands.extend(parse_fish(
+            "- cmd: echo a\\\\nb\n  when: 1700000003\n- cmd: ls\n",
+        ));
+
+        assert!(commands.contains(&"for f in *; do\n  echo $f\ndone".to_string()));
+        assert!(commands.contains(&"echo a\\nb".to_string()));
+
+        let ranked = rank(commands);
+        // Tied with git status on count, but run more recently.
+        assert_eq!(ranked[0].command, "ls");
+        assert_eq!(ranked[1].command, "git status");
+        assert_eq!(ranked[1].count, 3);
+        assert_eq!(ranked.len(), 4);
+    }
+
+    #[test]
+    fn test_zsh_history_is_unmetafied() {
+        // "echo café" as zsh writes it: é is C3 A9, and A9 is metafied.
+        let written = b": 1700000001:0;echo caf\xc3\x83\x89\n";
+        let content = String::from_utf8_lossy(&unmetafy(written)).into_owned();
+        assert_eq!(parse_zsh(&content), ["echo café"]);
+    }
+
+    #[test]
+    fn test_suggest_variables() {
+        let history = rank(vec![
+            "kubectl logs -n prod api".to_string(),
+            "kubectl logs -n dev web".to_string(),
+            "git checkout main".to_string(),
+            "git commit -m wip".to_string(),
+        ]);
+        assert_eq!(
+            suggest_variables("kubectl logs -n prod api", &history).unwrap(),
+            "kubectl logs -n {{n:prod}} {{arg1:api}}"
+        );
+        assert_eq!(suggest_variables("git checkout main", &history), None);
+        assert_eq!(
+            suggest_variables("git checkout dev", &history).unwrap(),
+            "git checkout {{arg1:dev}}"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use super::{atomic_write, AliasChange, SnippetChange};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// How many changes can be undone; older ones are forgotten.
+const KEEP: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StoreChange {
+    Alias(AliasChange),
+    Snippet(SnippetChange),
+}
+
+/// A change the user made and the change that takes it back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    /// As shown when it was saved, e.g. "Snippet 'Deploy' deleted".
+    pub description: String,
+    pub change: StoreChange,
+    pub undo: StoreChange,
+}
+
+/// The changes made from the TUI, newest last, so they can be undone and
+/// redone. Kept in the data directory, so the last session's changes can
+/// still be undone after a restart.
+pub struct Journal {
+    path: PathBuf,
+    data: JournalData,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct JournalData {
+    done: Vec<Operation>,
+    undone: Vec<Operation>,
+}
+
+impl Journal {
+    pub fn open() -> io::Result<Self> {
+        let dirs =
+            directories::ProjectDirs::from("com", "sniplias", "sniplias").ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Could not find data directory")
+            })?;
+        Self::in_file(dirs.data_dir().join("journal.json"))
+    }
+
+    pub fn in_file(path: PathBuf) -> io::Result<Self> {
+        let data = match fs::read_to_string(&path) {
+            Ok(content) if !content.trim().is_empty() => serde_json::from_str(&content)?,
+            Ok(_) => JournalData::default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => JournalData::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, data })
+    }
+
+    /// A journal that is never written, for when the data directory can't
+    /// be used.
+    pub fn in_memory() -> Self {
+        Self {
+            path: PathBuf::new(),
+            data: JournalData::default(),
+        }
+    }
+
+    /// Records a new change. Whatever was undone can no longer be redone.
+    pub fn record(&mut self, operation: Operation) -> io::Result<()> {
+        self.data.done.push(operation);
+        let excess = self.data.done.len().saturating_sub(KEEP);
+        self.data.done.drain(..excess);
+        self.data.undone.clear();
+        self.save()
+    }
+
+    /// The change the next undo takes back.
+    pub fn last_done(&self) -> Option<&Operation> {
+        self.data.done.last()
+    }
+
+    /// The change the next redo makes again.
+    pub fn last_undone(&self) -> Option<&Operation> {
+        self.data.undone.last()
+    }
+
+    /// Moves the last change to the redo list once it has been undone, or
+    /// drops it if it couldn't be.
+    pub fn finish_undo(&mut self, succeeded: bool) -> io::Result<()> {
+        if let Some(operation) = self.data.done.pop() {
+            if succeeded {
+                self.data.undone.push(operation);
+            }
+        }
+        self.save()
+    }
+
+    /// Moves the last undone change back once it has been redone, or drops
+    /// it if it couldn't be.
+    pub fn finish_redo(&mut self, succeeded: bool) -> io::Result<()> {
+        if let Some(operation) = self.data.undone.pop() {
+            if succeeded {
+                self.data.done.push(operation);
+            }
+        }
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if self.path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // It holds whatever was deleted, so only its owner may read it. New
+        // files are created that way; one from an older version is fixed
+        // here, and the rewrite keeps the mode.
+        #[cfg(unix)]
+        if self.path.exists() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+        }
+        atomic_write(
+            &self.path,
+            serde_json::to_string_pretty(&self.data)?.as_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Snippet;
+
+    #[test]
+    fn test_journal_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.json");
+        let mut snippet = Snippet::new("Deploy".into(), "make deploy".into());
+        snippet.library = "team".to_string();
+
+        let mut journal = Journal::in_file(path.clone()).unwrap();
+        journal
+            .record(Operation {
+                description: "Snippet 'Deploy' deleted".to_string(),
+                change: StoreChange::Snippet(SnippetChange::Delete(snippet.id)),
+                undo: StoreChange::Snippet(SnippetChange::Add(snippet.clone())),
+            })
+            .unwrap();
+        journal
+            .record(Operation {
+                description: "Alias 'gs' deleted".to_string(),
+                change: StoreChange::Alias(AliasChange::Delete("gs".into())),
+                undo: StoreChange::Alias(AliasChange::Delete("gs".into())),
+            })
+            .unwrap();
+        journal.finish_undo(true).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut reopened = Journal::in_file(path).unwrap();
+        assert_eq!(
+            reopened.last_undone().unwrap().description,
+            "Alias 'gs' deleted"
+        );
+        match &reopened.last_done().unwrap().undo {
+            StoreChange::Snippet(SnippetChange::Add(restored)) => {
+                assert_eq!(restored, &snippet);
+                assert_eq!(restored.library, "team");
+            }
+            other => panic!("unexpected undo {:?}", other),
+        }
+
+        // A failed undo is dropped rather than retried forever.
+        reopened.finish_undo(false).unwrap();
+        assert!(reopened.last_done().is_none());
+        assert!(reopened.last_undone().is_some());
+    }
+}
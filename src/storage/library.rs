@@ -0,0 +1,758 @@
+use super::atomic_write;
+use super::backup::{BackupEntry, Backups};
+use super::lock::LockFile;
+use super::snippet_dir::{self, file_backups, SnippetDir};
+use super::stamp::FileStamp;
+use super::sync::Synced;
+use super::SnippetStoreError;
+use crate::models::Snippet;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Name of the library kept in the user's data directory.
+pub const GLOBAL_LIBRARY: &str = "global";
+
+/// Project library files, looked for in the working directory and its parents.
+const PROJECT_FILE: &str = ".sniplias.toml";
+const PROJECT_DIR: &str = ".sniplias";
+
+/// Where a library came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    /// Found by walking up from the working directory.
+    Project,
+    /// Listed in the config file.
+    Registered,
+}
+
+/// How a library is laid out on disk, chosen by its path: a `.json` or
+/// `.toml` file, or a directory of them.
+#[derive(Debug, Clone)]
+enum Storage {
+    /// One file holding every snippet, as we last read or wrote it, to
+    /// notice edits made behind our back.
+    File(Option<FileStamp>),
+    /// One file per snippet.
+    Directory(SnippetDir),
+}
+
+/// On-disk format of a library, chosen by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// Schema version written to every library file. Files without one are
+/// version 0.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` upgrades a file from version `n` to `n + 1`. They work on
+/// the untyped document so they can see fields the current model has dropped.
+const MIGRATIONS: &[fn(&mut Value)] = &[pin_generated_fields];
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct LibraryData {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    snippets: Vec<Snippet>,
+    /// Top-level keys from newer versions, written back untouched.
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+impl LibraryData {
+    /// Parses `content` and migrates it up to [`SCHEMA_VERSION`], leaving
+    /// `version` as the file had it. Files from a newer version are read as
+    /// they are; unknown fields are kept.
+    fn parse(content: &str, format: Format) -> Result<Self, SnippetStoreError> {
+        if content.trim().is_empty() {
+            return Ok(Self {
+                version: SCHEMA_VERSION,
+                ..Self::default()
+            });
+        }
+        let mut document: Value = match format {
+            Format::Json => serde_json::from_str(content).map_err(SnippetStoreError::ParseError)?,
+            Format::Toml => toml::from_str(content).map_err(SnippetStoreError::TomlError)?,
+        };
+
+        let version = document.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+        for migrate in MIGRATIONS.iter().skip(version) {
+            migrate(&mut document);
+        }
+
+        serde_json::from_value(document).map_err(|e| {
+            // The untyped document has no positions; parse the text again to
+            // point at the offending line and column.
+            let located = match format {
+                Format::Json => serde_json::from_str::<Self>(content)
+                    .err()
+                    .map(SnippetStoreError::ParseError),
+                Format::Toml => toml::from_str::<Self>(content)
+                    .err()
+                    .map(SnippetStoreError::TomlError),
+            };
+            located.unwrap_or(SnippetStoreError::ParseError(e))
+        })
+    }
+
+    /// TOML writes multi-line commands as multi-line strings, literal ones if
+    /// they hold backslashes, so they read the same as in the terminal.
+    fn render(&self, format: Format) -> Result<String, SnippetStoreError> {
+        match format {
+            Format::Json => {
+                serde_json::to_string_pretty(self).map_err(SnippetStoreError::ParseError)
+            }
+            Format::Toml => toml::to_string_pretty(self).map_err(SnippetStoreError::TomlWriteError),
+        }
+    }
+}
+
+/// What was done about a library file that could not be parsed.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    pub library: String,
+    pub path: PathBuf,
+    /// The parse error, with line and column.
+    pub error: String,
+    /// Where the broken file was moved. Read-only libraries are left in place.
+    /// In a directory library, only the broken snippet file is moved.
+    pub quarantined: Option<PathBuf>,
+    /// The backup put in its place, if there was a good one.
+    pub restored: Option<BackupEntry>,
+}
+
+impl Recovery {
+    pub fn describe(&self) -> String {
+        let outcome = match (&self.quarantined, &self.restored) {
+            (None, _) => "It is read-only, so it was skipped.".to_string(),
+            (Some(moved), Some(backup)) => format!(
+                "Moved it to {} and restored the backup from {}.",
+                moved.display(),
+                backup
+                    .created_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+            ),
+            (Some(moved), None) => format!(
+                "Moved it to {}; no good backup was found, so its snippets are missing until you fix it.",
+                moved.display()
+            ),
+        };
+        format!(
+            "Library '{}' ({}) is damaged: {}\n{}",
+            self.library,
+            self.path.display(),
+            self.error,
+            outcome
+        )
+    }
+}
+
+/// One file of snippets. Each snippet remembers the library it came from,
+/// so edits are written back to the same file.
+pub struct Library {
+    pub name: String,
+    pub path: PathBuf,
+    pub scope: Scope,
+    /// Vendor packs and the like: readable and copyable, never written.
+    pub read_only: bool,
+    /// In file order, so saving rewrites only what changed; new snippets are
+    /// appended.
+    snippets: Vec<Snippet>,
+    /// Where copies go before each write; none for libraries opened directly.
+    backups: Option<Backups>,
+    /// Schema version of the file, before any migration.
+    version: u32,
+    /// Top-level fields we don't know about.
+    extra: Map<String, Value>,
+    storage: Storage,
+    /// The git working tree it is committed to on every save, if synced.
+    sync: Option<Synced>,
+}
+
+impl Library {
+    /// Opens the library at `path`, which may not exist yet.
+    #[cfg(test)]
+    pub fn open(name: String, path: PathBuf, scope: Scope) -> Result<Self, SnippetStoreError> {
+        let mut library = Self::new(name, path, scope);
+        if library.path.exists() {
+            library.load()?;
+        }
+        Ok(library)
+    }
+
+    fn new(name: String, path: PathBuf, scope: Scope) -> Self {
+        let storage = if snippet_dir::is_directory(&path) {
+            Storage::Directory(SnippetDir::default())
+        } else {
+            Storage::File(None)
+        };
+        Self {
+            name,
+            path,
+            scope,
+            read_only: false,
+            snippets: Vec::new(),
+            backups: None,
+            version: SCHEMA_VERSION,
+            extra: Map::new(),
+            storage,
+            sync: None,
+        }
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn with_backups(mut self, backups: Backups) -> Self {
+        self.backups = Some(backups);
+        self
+    }
+
+    pub fn with_sync(mut self, sync: Synced) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    pub fn sync(&self) -> Option<&Synced> {
+        self.sync.as_ref()
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self.storage, Storage::Directory(_))
+    }
+
+    /// Where `sniplias library split` puts the snippets of this file:
+    /// `team.json` becomes `team/`. None for a directory already, and for
+    /// `.sniplias.toml`, whose directory would be `.sniplias/` itself.
+    pub fn split_path(&self) -> Option<PathBuf> {
+        let project_file = self.path.file_name().is_some_and(|n| n == PROJECT_FILE);
+        (!self.is_directory() && !project_file).then(|| self.path.with_extension(""))
+    }
+
+    /// Commits the file with `message`, if the library is synced.
+    pub fn commit(&mut self, message: &str) {
+        if let Some(sync) = &mut self.sync {
+            sync.commit(&self.path, message);
+        }
+    }
+
+    /// Takes in the outcome of the commits made in the background.
+    pub fn poll_sync(&mut self) {
+        if let Some(sync) = &mut self.sync {
+            sync.poll();
+        }
+    }
+
+    #[cfg(test)]
+    pub fn wait_for_sync(&mut self) {
+        if let Some(sync) = &mut self.sync {
+            sync.wait();
+        }
+    }
+
+    /// Opens the library at `path` like [`Library::open`], but a file that
+    /// doesn't parse is moved aside and replaced by its newest good backup.
+    /// In a directory library, each damaged snippet file is handled alone.
+    pub fn open_or_recover(
+        name: String,
+        path: PathBuf,
+        scope: Scope,
+        read_only: bool,
+        backups: Backups,
+    ) -> Result<(Self, Vec<Recovery>), SnippetStoreError> {
+        let mut library = Self::new(name.clone(), path, scope)
+            .with_read_only(read_only)
+            .with_backups(backups);
+        if !library.path.exists() {
+            return Ok((library, Vec::new()));
+        }
+
+        let damaged = match library.storage {
+            Storage::Directory(_) => library.load_directory()?,
+            Storage::File(_) => match library.load() {
+                Ok(()) => Vec::new(),
+                Err(error) if error.is_corrupt() => vec![snippet_dir::Damaged {
+                    path: library.path.clone(),
+                    error,
+                }],
+                Err(e) => return Err(e),
+            },
+        };
+
+        let mut recoveries = Vec::new();
+        for snippet_dir::Damaged { path, error } in damaged {
+            let mut recovery = Recovery {
+                library: name.clone(),
+                path: path.clone(),
+                error: error.to_string(),
+                quarantined: None,
+                restored: None,
+            };
+            if !library.read_only {
+                let quarantined = quarantine_path(&path, &library.path);
+                fs::rename(&path, &quarantined).map_err(SnippetStoreError::WriteError)?;
+                recovery.quarantined = Some(quarantined);
+                recovery.restored = library.restore_backup(&path)?;
+            }
+            recoveries.push(recovery);
+        }
+        if recoveries.iter().any(|r| r.restored.is_some()) {
+            library.load()?;
+        }
+
+        Ok((library, recoveries))
+    }
+
+    /// Writes the newest backup of `path` that parses back in its place.
+    fn restore_backup(&self, path: &Path) -> Result<Option<BackupEntry>, SnippetStoreError> {
+        let Some(backups) = self.backups_of(path) else {
+            return Ok(None);
+        };
+        for entry in backups.list().map_err(SnippetStoreError::ReadError)? {
+            let Ok(content) = fs::read_to_string(&entry.path) else {
+                continue;
+            };
+            if self.check(path, &content).is_ok() {
+                atomic_write(path, content.as_bytes()).map_err(SnippetStoreError::WriteError)?;
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Puts the quarantined copy of `path` back once it parses again,
+    /// backing up what is there now.
+    pub fn reinstate(&mut self, quarantined: &Path, path: &Path) -> Result<(), SnippetStoreError> {
+        let content = fs::read_to_string(quarantined).map_err(SnippetStoreError::ReadError)?;
+        self.check(path, &content)?;
+
+        self.backup_current(path)?;
+        atomic_write(path, content.as_bytes()).map_err(SnippetStoreError::WriteError)?;
+        fs::remove_file(quarantined).map_err(SnippetStoreError::WriteError)?;
+        self.load()
+    }
+
+    /// Whether `content` would parse as the file at `path`: the whole
+    /// library, or one snippet of a directory library.
+    fn check(&self, path: &Path, content: &str) -> Result<(), SnippetStoreError> {
+        match self.storage {
+            Storage::File(_) => LibraryData::parse(content, self.format()).map(|_| ()),
+            Storage::Directory(_) => {
+                snippet_dir::parse_snippet(content, Format::from_path(path)).map(|_| ())
+            }
+        }
+    }
+
+    /// The backup rotation of `path`, the library file or one of the files
+    /// of a directory library.
+    fn backups_of(&self, path: &Path) -> Option<Backups> {
+        let backups = self.backups.as_ref()?;
+        Some(match self.storage {
+            Storage::File(_) => backups.clone(),
+            Storage::Directory(_) => file_backups(backups, path),
+        })
+    }
+
+    /// Copies `path` as it is on disk into its backup rotation, if any.
+    fn backup_current(&self, path: &Path) -> Result<(), SnippetStoreError> {
+        let Some(backups) = self.backups_of(path) else {
+            return Ok(());
+        };
+        match fs::read(path) {
+            Ok(current) => backups
+                .create(&current)
+                .map(|_| ())
+                .map_err(SnippetStoreError::WriteError),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(SnippetStoreError::ReadError(e)),
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        Format::from_path(&self.path)
+    }
+
+    pub fn load(&mut self) -> Result<(), SnippetStoreError> {
+        if let Storage::Directory(_) = self.storage {
+            return match self.load_directory()?.into_iter().next() {
+                Some(damaged) => Err(damaged.error),
+                None => Ok(()),
+            };
+        }
+        let content = fs::read_to_string(&self.path).map_err(SnippetStoreError::ReadError)?;
+
+        let data = LibraryData::parse(&content, self.format())?;
+        self.version = data.version;
+
+        self.set_snippets(data.snippets);
+        self.extra = data.extra;
+        self.storage = Storage::File(Some(FileStamp::new(content.as_bytes())));
+
+        Ok(())
+    }
+
+    /// Takes the snippets as read, in order. A repeated id keeps the place
+    /// of its first copy and the contents of its last, as before.
+    fn set_snippets(&mut self, snippets: Vec<Snippet>) {
+        self.snippets.clear();
+        for mut snippet in snippets {
+            snippet.library = self.name.clone();
+            self.insert(snippet);
+        }
+    }
+
+    /// Loads the snippet files that parse and returns those that don't.
+    fn load_directory(&mut self) -> Result<Vec<snippet_dir::Damaged>, SnippetStoreError> {
+        let (files, loaded) = SnippetDir::load(&self.path)?;
+        // Directories carry no version; old files only lack generated fields.
+        self.version = if loaded.migrated { 0 } else { SCHEMA_VERSION };
+        self.set_snippets(loaded.snippets);
+        self.storage = Storage::Directory(files);
+        Ok(loaded.damaged)
+    }
+
+    /// Whether the file on disk predates [`SCHEMA_VERSION`].
+    pub fn is_outdated(&self) -> bool {
+        let loaded = match &self.storage {
+            Storage::File(stamp) => stamp.is_some(),
+            Storage::Directory(_) => self.path.exists(),
+        };
+        loaded && self.version < SCHEMA_VERSION
+    }
+
+    /// Rewrites an outdated file in the current schema, after backing up the
+    /// original. Returns whether anything was written.
+    pub fn upgrade(&mut self) -> Result<bool, SnippetStoreError> {
+        if !self.is_outdated() || self.read_only {
+            return Ok(false);
+        }
+        self.save()?;
+        self.version = SCHEMA_VERSION;
+        Ok(true)
+    }
+
+    pub fn save(&mut self) -> Result<(), SnippetStoreError> {
+        if self.read_only {
+            return Err(SnippetStoreError::ReadOnly(self.name.clone()));
+        }
+        if let Storage::Directory(files) = &mut self.storage {
+            let _lock = LockFile::acquire(&self.path).map_err(SnippetStoreError::LockError)?;
+            return files.save(&self.path, &self.snippets, self.backups.as_ref());
+        }
+        let data = LibraryData {
+            version: self.version.max(SCHEMA_VERSION),
+            snippets: self.snippets.clone(),
+            extra: self.extra.clone(),
+        };
+        let content = data.render(self.format())?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(SnippetStoreError::WriteError)?;
+        }
+
+        let _lock = LockFile::acquire(&self.path).map_err(SnippetStoreError::LockError)?;
+
+        let on_disk = FileStamp::read(&self.path).map_err(SnippetStoreError::ReadError)?;
+        if !matches!(self.storage, Storage::File(stamp) if stamp == on_disk) {
+            return Err(SnippetStoreError::Conflict(self.path.clone()));
+        }
+
+        self.backup_current(&self.path)?;
+        atomic_write(&self.path, content.as_bytes()).map_err(SnippetStoreError::WriteError)?;
+        self.storage = Storage::File(Some(FileStamp::new(content.as_bytes())));
+
+        Ok(())
+    }
+
+    /// The snippets in file order.
+    pub fn snippets(&self) -> impl Iterator<Item = &Snippet> {
+        self.snippets.iter()
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&Snippet> {
+        self.snippets.iter().find(|s| s.id == *id)
+    }
+
+    /// Replaces the snippet with the same id where it stands, or appends it.
+    pub(super) fn insert(&mut self, snippet: Snippet) {
+        match self.snippets.iter_mut().find(|s| s.id == snippet.id) {
+            Some(existing) => *existing = snippet,
+            None => self.snippets.push(snippet),
+        }
+    }
+
+    pub(super) fn remove(&mut self, id: &Uuid) -> Option<Snippet> {
+        let index = self.snippets.iter().position(|s| s.id == *id)?;
+        Some(self.snippets.remove(index))
+    }
+
+    pub(super) fn snippets_mut(&mut self) -> &mut Vec<Snippet> {
+        &mut self.snippets
+    }
+}
+
+/// 0 → 1: ids and timestamps used to be filled in afresh on every load when
+/// missing, so a snippet's id changed between runs. Write them down once.
+pub(super) fn pin_generated_fields(document: &mut Value) {
+    let Some(snippets) = document.get_mut("snippets").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let now = Value::from(chrono::Utc::now().to_rfc3339());
+    for snippet in snippets.iter_mut().filter_map(Value::as_object_mut) {
+        snippet
+            .entry("id")
+            .or_insert_with(|| Value::from(Uuid::new_v4().to_string()));
+        let created_at = snippet.entry("created_at").or_insert(now.clone()).clone();
+        snippet.entry("updated_at").or_insert(created_at);
+    }
+}
+
+/// `snippets.json.corrupt-<timestamp>` next to the file. A broken file of a
+/// directory library goes next to the directory, as `team.deploy.toml.corrupt-…`,
+/// so it's neither loaded nor committed with the library.
+fn quarantine_path(path: &Path, library: &Path) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    if path != library {
+        let dir = library.file_name().unwrap_or_default().to_string_lossy();
+        name = format!("{}.{}", dir, name);
+    }
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S");
+    library.with_file_name(format!("{}.corrupt-{}", name, stamp))
+}
+
+/// Rewrites the library `from` as `to`, each a file in the format its
+/// extension names or a directory of one file per snippet. Returns how many
+/// snippets were written.
+pub fn convert(from: &Path, to: &Path) -> Result<usize, SnippetStoreError> {
+    if to.exists() {
+        return Err(SnippetStoreError::WriteError(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        )));
+    }
+    let mut data = if from.is_dir() {
+        let (_, loaded) = SnippetDir::load(from)?;
+        if let Some(damaged) = loaded.damaged.into_iter().next() {
+            return Err(damaged.error);
+        }
+        LibraryData {
+            snippets: loaded.snippets,
+            ..LibraryData::default()
+        }
+    } else {
+        let content = fs::read_to_string(from).map_err(SnippetStoreError::ReadError)?;
+        LibraryData::parse(&content, Format::from_path(from))?
+    };
+    data.version = data.version.max(SCHEMA_VERSION);
+
+    if let Some(parent) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(SnippetStoreError::WriteError)?;
+    }
+    if snippet_dir::is_directory(to) {
+        SnippetDir::default().save(to, &data.snippets, None)?;
+    } else {
+        let converted = data.render(Format::from_path(to))?;
+        atomic_write(to, converted.as_bytes()).map_err(SnippetStoreError::WriteError)?;
+    }
+    Ok(data.snippets.len())
+}
+
+/// Project libraries from `start` up to the filesystem root, nearest first:
+/// a `.sniplias.toml` file, and any `.toml`/`.json` files and directories of
+/// them in a `.sniplias/` directory.
+pub fn discover_project_libraries(start: &Path) -> Vec<(String, PathBuf)> {
+    let mut found = Vec::new();
+
+    for dir in start.ancestors() {
+        let project = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "/".to_string());
+
+        let file = dir.join(PROJECT_FILE);
+        if file.is_file() {
+            found.push((project.clone(), file));
+        }
+
+        let Ok(entries) = fs::read_dir(dir.join(PROJECT_DIR)) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                let hidden = p
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+                let library_file = p.is_file()
+                    && matches!(
+                        p.extension().and_then(|e| e.to_str()),
+                        Some("toml" | "json")
+                    );
+                !hidden && (library_file || p.is_dir())
+            })
+            .collect();
+        files.sort();
+        for path in files {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            found.push((format!("{}/{}", project, stem), path));
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_round_trips_between_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("snippets.json");
+        let toml = dir.path().join("snippets.toml");
+        let back = dir.path().join("back.json");
+
+        let snippet = Snippet::new("Loop".into(), "for f in *; do\n  echo \"$f\"\ndone".into())
+            .with_description("it's 'quoted'".into());
+        let data = LibraryData {
+            version: SCHEMA_VERSION,
+            snippets: vec![snippet.clone(), Snippet::new("ls".into(), "ls -la".into())],
+            ..LibraryData::default()
+        };
+        fs::write(&json, data.render(Format::Json).unwrap()).unwrap();
+
+        assert_eq!(convert(&json, &toml).unwrap(), 2);
+        let written = fs::read_to_string(&toml).unwrap();
+        assert!(written.contains("for f in *; do\n  echo \"$f\"\ndone"));
+
+        convert(&toml, &back).unwrap();
+        assert_eq!(
+            fs::read_to_string(&back).unwrap(),
+            fs::read_to_string(&json).unwrap()
+        );
+        assert!(convert(&json, &toml).is_err());
+    }
+
+    #[test]
+    fn test_damaged_file_is_quarantined_and_backup_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snippets.json");
+        let backups = Backups::in_dir(dir.path().join("backups"), &path);
+        backups
+            .create(br#"{"version": 1, "snippets": []}"#)
+            .unwrap();
+        fs::write(&path, "{\n  \"snippets\": [\n    {\"title\": 1}\n  ]\n}").unwrap();
+
+        let (library, recoveries) =
+            Library::open_or_recover("global".into(), path.clone(), Scope::Global, false, backups)
+                .unwrap();
+        assert_eq!(recoveries.len(), 1);
+        let recovery = recoveries.into_iter().next().unwrap();
+        assert!(recovery.error.contains("line 3"), "{}", recovery.error);
+        assert!(recovery.restored.is_some());
+        let quarantined = recovery.quarantined.unwrap();
+        assert!(fs::read_to_string(&quarantined)
+            .unwrap()
+            .contains("\"title\": 1"));
+        assert_eq!(library.snippets().count(), 0);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"version\": 1"));
+    }
+
+    #[test]
+    fn test_directory_library_quarantines_only_the_damaged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("team.json");
+        let root = dir.path().join("team");
+        let data = LibraryData {
+            version: SCHEMA_VERSION,
+            snippets: vec![
+                Snippet::new("Deploy".into(), "make deploy".into()),
+                Snippet::new("Logs".into(), "kubectl logs".into()),
+            ],
+            ..LibraryData::default()
+        };
+        fs::write(&json, data.render(Format::Json).unwrap()).unwrap();
+        assert_eq!(convert(&json, &root).unwrap(), 2);
+        fs::write(root.join("logs.toml"), "title = 1\n").unwrap();
+
+        let backups = Backups::in_dir(dir.path().join("backups"), &root);
+        let (mut library, recoveries) = Library::open_or_recover(
+            "team".into(),
+            root.clone(),
+            Scope::Registered,
+            false,
+            backups,
+        )
+        .unwrap();
+        assert_eq!(recoveries.len(), 1);
+        assert_eq!(recoveries[0].path, root.join("logs.toml"));
+        assert!(!root.join("logs.toml").exists());
+        assert_eq!(library.snippets().count(), 1);
+
+        let build = Snippet::new("Build".into(), "make".into());
+        library.insert(build);
+        library.save().unwrap();
+        assert!(root.join("build.toml").exists());
+        assert!(library.is_directory() && library.split_path().is_none());
+    }
+
+    #[test]
+    fn test_old_files_are_migrated_and_unknown_fields_kept() {
+        let old = r#"{"snippets": [{"title": "ls", "command": "ls", "description": null}]}"#;
+        let data = LibraryData::parse(old, Format::Json).unwrap();
+        assert_eq!(data.version, 0);
+        let rendered = data.render(Format::Json).unwrap();
+        let again = LibraryData::parse(&rendered, Format::Json).unwrap();
+        assert_eq!(again.snippets[0].id, data.snippets[0].id);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snippets.json");
+        fs::write(
+            &path,
+            r#"{"version": 7, "tags": ["x"], "snippets": [{"title": "ls", "command": "ls",
+                "description": null, "pinned": true}]}"#,
+        )
+        .unwrap();
+        let mut library = Library::open("global".into(), path.clone(), Scope::Global).unwrap();
+        assert!(!library.is_outdated());
+        library.save().unwrap();
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], 7);
+        assert_eq!(saved["tags"][0], "x");
+        assert_eq!(saved["snippets"][0]["pinned"], true);
+
+        let mut library = library.with_read_only(true);
+        library.insert(Snippet::new("Build".into(), "make".into()));
+        assert!(matches!(
+            library.save(),
+            Err(SnippetStoreError::ReadOnly(_))
+        ));
+        assert!(!fs::read_to_string(&path).unwrap().contains("make"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Aliases as the user's interactive shell actually sees them, including
+//! ones defined by plugins and frameworks outside the rc file we manage.
+
+use super::{AliasStore, ExternalAliases};
+use crate::models::{Alias, AliasSource};
+use crate::utils::output_detached;
+use std::io;
+use std::process::Command;
+use std::time::Duration;
+
+/// How long an interactive shell may take to start up, plugins and all.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs `$SHELL -ic alias` and parses what it prints. The shell gets no
+/// terminal, so one stuck on a prompt is given up on after a while.
+pub fn live_aliases(source: &AliasSource) -> io::Result<Vec<Alias>> {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| source.as_str().to_string());
+    // zsh's plain `alias` drops the -g/-s flags; `-L` lists them as definitions.
+    let list = if shell.ends_with("zsh") {
+        "alias -L"
+    } else {
+        "alias"
+    };
+
+    let mut command = Command::new(&shell);
+    command.args(["-ic", list]);
+    let output = output_detached(command, TIMEOUT)
+        .map_err(|e| io::Error::new(e.kind(), format!("`{} -ic {}`: {}", shell, list, e)))?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`{} -ic {}` exited with {}",
+            shell, list, output.status
+        )));
+    }
+
+    Ok(parse_alias_output(
+        &String::from_utf8_lossy(&output.stdout),
+        source,
+    ))
+}
+
+/// The `live` aliases that are neither in the rc file nor already marked
+/// external, ready to be adopted with the store's shell.
+pub fn unmanaged_aliases(
+    live: Vec<Alias>,
+    store: &AliasStore,
+    externals: &ExternalAliases,
+) -> Vec<Alias> {
+    let mut aliases: Vec<Alias> = live
+        .into_iter()
+        .filter(|a| store.get(&a.name).is_none() && !externals.contains(&a.name))
+        .map(|mut a| {
+            a.source_file = store.source().clone();
+            a
+        })
+        .collect();
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    aliases
+}
+
+/// Splits `aliases` into those that can be written to the rc file and the
+/// reasons the rest can't, so one bad name doesn't hold up the others.
+pub fn adoptable(aliases: Vec<Alias>) -> (Vec<Alias>, Vec<String>) {
+    let mut skipped = Vec::new();
+    let aliases = aliases
+        .into_iter()
+        .filter(|alias| match alias.validate() {
+            Ok(()) => true,
+            Err(reason) => {
+                skipped.push(reason);
+                false
+            }
+        })
+        .collect();
+    (aliases, skipped)
+}
+
+/// Bash prints `alias name='value'`; zsh's plain `alias` leaves off the
+/// keyword, so it is added back before parsing.
+fn parse_alias_output(output: &str, source: &AliasSource) -> Vec<Alias> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with("alias ") {
+                Alias::parse_line(line, source.clone())
+            } else {
+                Alias::parse_line(&format!("alias {}", line), source.clone())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AliasKind;
+
+    #[test]
+    fn test_parse_bash_and_zsh_output() {
+        let bash = parse_alias_output(
+            "alias gst='git status'\nalias ll='ls -la'\n",
+            &AliasSource::Bash,
+        );
+        assert_eq!(bash.len(), 2);
+        assert_eq!(bash[0].command, "git status");
+
+        let zsh = parse_alias_output("gst='git status'\nalias -g G='| grep'\n", &AliasSource::Zsh);
+        assert_eq!(zsh[0].name, "gst");
+        assert_eq!(zsh[1].kind, AliasKind::Global);
+    }
+
+    #[test]
+    fn test_bad_names_are_skipped_one_by_one() {
+        let (aliases, skipped) = adoptable(parse_alias_output(
+            "alias gst='git status'\nalias 'g s'='git status'\nalias ll='ls -la'\n",
+            &AliasSource::Bash,
+        ));
+        let names: Vec<_> = aliases.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["gst", "ll"]);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("'g s'"), "{}", skipped[0]);
+    }
+}
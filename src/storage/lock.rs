@@ -0,0 +1,62 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// A lock older than this is assumed to belong to a crashed process.
+const STALE_AFTER: Duration = Duration::from_secs(30);
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+const RETRIES: u32 = 40;
+
+/// An advisory lock held as `<file>.lock` next to the file it protects.
+///
+/// Only sniplias honours it; it keeps two instances from interleaving their
+/// read-check-write cycles. The lock file is removed when this is dropped.
+pub struct LockFile {
+    path: PathBuf,
+}
+
+impl LockFile {
+    pub fn acquire(target: &Path) -> io::Result<Self> {
+        let mut name = target.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let path = target.with_file_name(name);
+
+        for _ in 0..RETRIES {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if Self::is_stale(&path) {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    std::thread::sleep(RETRY_DELAY);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            format!("{} is held by another sniplias process", path.display()),
+        ))
+    }
+
+    fn is_stale(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age > STALE_AFTER)
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
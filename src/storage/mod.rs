@@ -1,5 +1,29 @@
 mod alias_store;
+mod atomic;
+mod backup;
+mod config;
+mod diff;
+mod external;
+mod generated;
+mod history;
+mod journal;
+mod library;
+mod live_shell;
+mod lock;
+mod rc_file;
+mod snippet_dir;
 mod snippet_store;
+mod stamp;
+mod sync;
 
-pub use alias_store::AliasStore;
-pub use snippet_store::SnippetStore;
+pub use alias_store::{AliasChange, AliasStore, AliasStoreError};
+pub use atomic::atomic_write;
+pub use backup::{BackupEntry, Backups};
+pub use config::{AliasMode, Config, LibraryConfig};
+pub use external::ExternalAliases;
+pub use history::{load_history, suggest_variables, HistoryEntry};
+pub use journal::{Journal, Operation, StoreChange};
+pub use library::{convert as convert_library, Library, Recovery, Scope, GLOBAL_LIBRARY};
+pub use live_shell::{adoptable, live_aliases, unmanaged_aliases};
+pub use snippet_store::{SnippetChange, SnippetStore, SnippetStoreError};
+pub use sync::{GitRepo, Synced};
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+pub const MANAGED_BLOCK_START: &str = "# SNIPLIAS ALIASES START";
+pub const MANAGED_BLOCK_END: &str = "# SNIPLIAS ALIASES END";
+
+/// A shell rc file held as raw lines so edits can touch only what changed.
+///
+/// Every line keeps its original terminator, which means rendering a
+/// document without edits reproduces the file byte for byte.
+#[derive(Debug, Clone, Default)]
+pub struct RcFile {
+    lines: Vec<String>,
+}
+
+/// A single change to an [`RcFile`], expressed against its line numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RcEdit {
+    /// Replace the lines in `span` with `text`, keeping the indentation of
+    /// the first replaced line.
+    Replace { span: Range<usize>, text: String },
+    /// Drop the lines in `span`.
+    Remove { span: Range<usize> },
+    /// Add `text` to the end of the managed block, creating the block if needed.
+    Append { text: String },
+}
+
+enum LineAction<'a> {
+    Replace(&'a str),
+    Remove,
+}
+
+impl RcFile {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            lines: content.split_inclusive('\n').map(str::to_string).collect(),
+        }
+    }
+
+    /// Lines without their terminators, paired with their line number.
+    pub fn lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim_end_matches(['\n', '\r'])))
+    }
+
+    /// Line range of the managed block markers, if the file has one.
+    pub fn managed_block(&self) -> Option<Range<usize>> {
+        let start = self
+            .lines()
+            .find(|(_, line)| line.contains(MANAGED_BLOCK_START))?
+            .0;
+        let end = self
+            .lines()
+            .skip(start + 1)
+            .find(|(_, line)| line.contains(MANAGED_BLOCK_END))?
+            .0;
+        Some(start..end + 1)
+    }
+
+    /// Renders the document with `edits` applied. Lines not covered by an
+    /// edit are copied through unchanged.
+    pub fn render(&self, edits: &[RcEdit]) -> String {
+        let mut actions: HashMap<usize, LineAction> = HashMap::new();
+        let mut appended: Vec<&str> = Vec::new();
+
+        for edit in edits {
+            match edit {
+                RcEdit::Replace { span, text } => {
+                    for line in span.clone() {
+                        actions.insert(line, LineAction::Remove);
+                    }
+                    actions.insert(span.start, LineAction::Replace(text));
+                }
+                RcEdit::Remove { span } => {
+                    for line in span.clone() {
+                        actions.insert(line, LineAction::Remove);
+                    }
+                }
+                RcEdit::Append { text } => appended.push(text),
+            }
+        }
+
+        let block_end = self.managed_block().map(|block| block.end - 1);
+        let mut output = String::new();
+
+        for (i, raw) in self.lines.iter().enumerate() {
+            if Some(i) == block_end {
+                for text in &appended {
+                    output.push_str(text);
+                    output.push('\n');
+                }
+            }
+
+            match actions.get(&i) {
+                None => output.push_str(raw),
+                Some(LineAction::Remove) => {}
+                Some(LineAction::Replace(text)) => {
+                    let indent: String = raw.chars().take_while(|c| c.is_whitespace()).collect();
+                    let terminator = self.span_terminator(i, edits);
+                    let body = text
+                        .lines()
+                        .map(|line| format!("{}{}", indent, line))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    output.push_str(&body);
+                    output.push_str(terminator);
+                }
+            }
+        }
+
+        if block_end.is_none() && !appended.is_empty() {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push('\n');
+            output.push_str(MANAGED_BLOCK_START);
+            output.push('\n');
+            for text in &appended {
+                output.push_str(text);
+                output.push('\n');
+            }
+            output.push_str(MANAGED_BLOCK_END);
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// The terminator of the last line of the replaced span starting at `start`.
+    fn span_terminator(&self, start: usize, edits: &[RcEdit]) -> &str {
+        let last = edits
+            .iter()
+            .find_map(|edit| match edit {
+                RcEdit::Replace { span, .. } if span.start == start => Some(span.end - 1),
+                _ => None,
+            })
+            .unwrap_or(start);
+        let raw = &self.lines[last];
+        &raw[raw.trim_end_matches(['\n', '\r']).len()..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_without_edits_is_identity() {
+        let content = "# comment\nalias ll='ls -la'\r\nif true; then\n  alias g='git'\nfi";
+        assert_eq!(RcFile::parse(content).render(&[]), content);
+    }
+
+    #[test]
+    fn test_replace_keeps_indentation_and_neighbours() {
+        let content = "if true; then\n  alias g='git'\nfi\n";
+        let file = RcFile::parse(content);
+        let rendered = file.render(&[RcEdit::Replace {
+            span: 1..2,
+            text: "alias g='git status'".to_string(),
+        }]);
+        assert_eq!(rendered, "if true; then\n  alias g='git status'\nfi\n");
+    }
+
+    #[test]
+    fn test_append_goes_into_managed_block() {
+        let content = format!(
+            "alias a='1'\n{}\nalias b='2'\n{}\n# trailer\n",
+            MANAGED_BLOCK_START, MANAGED_BLOCK_END
+        );
+        let file = RcFile::parse(&content);
+        let rendered = file.render(&[
+            RcEdit::Remove { span: 0..1 },
+            RcEdit::Append {
+                text: "alias c='3'".to_string(),
+            },
+        ]);
+        assert_eq!(
+            rendered,
+            format!(
+                "{}\nalias b='2'\nalias c='3'\n{}\n# trailer\n",
+                MANAGED_BLOCK_START, MANAGED_BLOCK_END
+            )
+        );
+    }
+
+    #[test]
+    fn test_append_creates_managed_block() {
+        let file = RcFile::parse("export A=1");
+        let rendered = file.render(&[RcEdit::Append {
+            text: "alias c='3'".to_string(),
+        }]);
+        assert_eq!(
+            rendered,
+            format!(
+                "export A=1\n\n{}\nalias c='3'\n{}\n",
+                MANAGED_BLOCK_START, MANAGED_BLOCK_END
+            )
+        );
+    }
+}
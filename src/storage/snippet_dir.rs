@@ -0,0 +1,286 @@
+//! The directory layout of a library: one file per snippet, so edits to
+//! different snippets never touch the same file and merge cleanly.
+
+use super::atomic_write;
+use super::backup::Backups;
+use super::library::{pin_generated_fields, Format};
+use super::stamp::FileStamp;
+use super::SnippetStoreError;
+use crate::models::Snippet;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Whether `path` holds a directory library: an existing directory, or a
+/// path without a `.json` or `.toml` extension that doesn't exist yet.
+pub fn is_directory(path: &Path) -> bool {
+    path.is_dir() || (!path.exists() && path.extension().is_none())
+}
+
+/// A snippet file that didn't parse.
+#[derive(Debug)]
+pub struct Damaged {
+    pub path: PathBuf,
+    pub error: SnippetStoreError,
+}
+
+/// What was read from a directory library.
+#[derive(Debug, Default)]
+pub struct Loaded {
+    pub snippets: Vec<Snippet>,
+    pub damaged: Vec<Damaged>,
+    /// Some files lacked an id or timestamps, which were filled in.
+    pub migrated: bool,
+}
+
+/// The files of a directory library as we last read or wrote them, by
+/// snippet id, to write only what changed and notice edits made behind our
+/// back.
+#[derive(Debug, Clone, Default)]
+pub struct SnippetDir {
+    files: HashMap<Uuid, (PathBuf, FileStamp)>,
+}
+
+impl SnippetDir {
+    /// Reads every `.toml` and `.json` file in `dir`. Files that don't parse
+    /// are left out and reported.
+    pub fn load(dir: &Path) -> Result<(Self, Loaded), SnippetStoreError> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(SnippetStoreError::ReadError)?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && is_snippet_file(p))
+            .collect();
+        paths.sort();
+
+        let mut this = Self::default();
+        let mut loaded = Loaded::default();
+        for path in paths {
+            let content = fs::read_to_string(&path).map_err(SnippetStoreError::ReadError)?;
+            match parse_snippet(&content, Format::from_path(&path)) {
+                Ok((snippet, migrated)) => {
+                    loaded.migrated |= migrated;
+                    this.files
+                        .insert(snippet.id, (path, FileStamp::new(content.as_bytes())));
+                    loaded.snippets.push(snippet);
+                }
+                Err(error) => loaded.damaged.push(Damaged { path, error }),
+            }
+        }
+        Ok((this, loaded))
+    }
+
+    /// Writes the snippets whose file would change, creates files for new
+    /// ones and removes those of deleted ones. Nothing is written if one of
+    /// those files was changed by another program. Old contents go to
+    /// `backups`, one rotation per file.
+    pub fn save<'a>(
+        &mut self,
+        dir: &Path,
+        snippets: impl IntoIterator<Item = &'a Snippet>,
+        backups: Option<&Backups>,
+    ) -> Result<(), SnippetStoreError> {
+        let mut writes: Vec<(Uuid, PathBuf, String)> = Vec::new();
+        let mut kept: Vec<Uuid> = Vec::new();
+        let mut taken: Vec<PathBuf> = self.files.values().map(|(p, _)| p.clone()).collect();
+
+        for snippet in snippets {
+            kept.push(snippet.id);
+            let path = match self.files.get(&snippet.id) {
+                Some((path, _)) => path.clone(),
+                None => {
+                    let path = new_file_path(dir, snippet, &taken);
+                    taken.push(path.clone());
+                    path
+                }
+            };
+            let content = render_snippet(snippet, Format::from_path(&path))?;
+            let unchanged = self
+                .files
+                .get(&snippet.id)
+                .is_some_and(|(_, stamp)| *stamp == FileStamp::new(content.as_bytes()));
+            if !unchanged {
+                writes.push((snippet.id, path, content));
+            }
+        }
+        let removed: Vec<Uuid> = self
+            .files
+            .keys()
+            .filter(|id| !kept.contains(id))
+            .copied()
+            .collect();
+
+        // Check every file we are about to touch before touching any.
+        let touched = writes
+            .iter()
+            .map(|(id, path, _)| (id, path))
+            .chain(removed.iter().map(|id| (id, &self.files[id].0)));
+        for (id, path) in touched {
+            let expected = self.files.get(id).map(|(_, stamp)| *stamp);
+            let on_disk = FileStamp::read(path).map_err(SnippetStoreError::ReadError)?;
+            if on_disk != expected {
+                return Err(SnippetStoreError::Conflict(path.clone()));
+            }
+        }
+
+        fs::create_dir_all(dir).map_err(SnippetStoreError::WriteError)?;
+        for (id, path, content) in writes {
+            if self.files.contains_key(&id) {
+                backup(&path, backups)?;
+            }
+            atomic_write(&path, content.as_bytes()).map_err(SnippetStoreError::WriteError)?;
+            self.files
+                .insert(id, (path, FileStamp::new(content.as_bytes())));
+        }
+        for id in removed {
+            let (path, _) = self.files.remove(&id).unwrap();
+            backup(&path, backups)?;
+            fs::remove_file(&path).map_err(SnippetStoreError::WriteError)?;
+        }
+        Ok(())
+    }
+}
+
+/// The backup rotation of one file in a directory library.
+pub fn file_backups(backups: &Backups, path: &Path) -> Backups {
+    Backups::in_dir(backups.dir().to_path_buf(), path)
+}
+
+fn backup(path: &Path, backups: Option<&Backups>) -> Result<(), SnippetStoreError> {
+    let Some(backups) = backups else {
+        return Ok(());
+    };
+    let content = fs::read(path).map_err(SnippetStoreError::ReadError)?;
+    file_backups(backups, path)
+        .create(&content)
+        .map(|_| ())
+        .map_err(SnippetStoreError::WriteError)
+}
+
+fn is_snippet_file(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+    !hidden
+        && matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("toml" | "json")
+        )
+}
+
+/// One snippet per file. Returns whether an id or timestamp had to be
+/// filled in, which the caller should write down.
+pub fn parse_snippet(content: &str, format: Format) -> Result<(Snippet, bool), SnippetStoreError> {
+    let document: Value = match format {
+        Format::Json => serde_json::from_str(content).map_err(SnippetStoreError::ParseError)?,
+        Format::Toml => toml::from_str(content).map_err(SnippetStoreError::TomlError)?,
+    };
+    let migrated = ["id", "created_at", "updated_at"]
+        .iter()
+        .any(|key| document.get(key).is_none());
+    let mut wrapped = serde_json::json!({ "snippets": [document] });
+    pin_generated_fields(&mut wrapped);
+    let document = wrapped["snippets"][0].take();
+
+    let snippet = serde_json::from_value(document).map_err(|e| {
+        // As for whole libraries, parse the text again for a line and column.
+        let located = match format {
+            Format::Json => serde_json::from_str::<Snippet>(content)
+                .err()
+                .map(SnippetStoreError::ParseError),
+            Format::Toml => toml::from_str::<Snippet>(content)
+                .err()
+                .map(SnippetStoreError::TomlError),
+        };
+        located.unwrap_or(SnippetStoreError::ParseError(e))
+    })?;
+    Ok((snippet, migrated))
+}
+
+/// Keys come out in the same order every time: the model's fields, then
+/// unknown ones sorted by name.
+pub fn render_snippet(snippet: &Snippet, format: Format) -> Result<String, SnippetStoreError> {
+    match format {
+        Format::Json => serde_json::to_string_pretty(snippet)
+            .map(|json| json + "\n")
+            .map_err(SnippetStoreError::ParseError),
+        Format::Toml => toml::to_string_pretty(snippet).map_err(SnippetStoreError::TomlWriteError),
+    }
+}
+
+/// `deploy-to-staging.toml` from the title, with the start of the id added
+/// if that name is taken. The name stays when the title changes later.
+fn new_file_path(dir: &Path, snippet: &Snippet, taken: &[PathBuf]) -> PathBuf {
+    let slug: String = snippet
+        .title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .take(8)
+        .collect::<Vec<_>>()
+        .join("-");
+    let id = snippet.id.simple().to_string();
+    let slug = if slug.is_empty() {
+        id[..8].to_string()
+    } else {
+        slug
+    };
+
+    let free = |path: &PathBuf| !path.exists() && !taken.contains(path);
+    let path = dir.join(format!("{}.toml", slug));
+    if free(&path) {
+        return path;
+    }
+    let path = dir.join(format!("{}-{}.toml", slug, &id[..8]));
+    if free(&path) {
+        return path;
+    }
+    dir.join(format!("{}.toml", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_touches_only_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("team");
+        let deploy = Snippet::new("Deploy to staging!".into(), "make deploy".into());
+        let twin = Snippet::new("Deploy to staging".into(), "make deploy2".into());
+        let logs = Snippet::new("Logs".into(), "kubectl logs {{pod}}".into());
+
+        let mut files = SnippetDir::default();
+        files.save(&root, [&deploy, &twin, &logs], None).unwrap();
+        let deploy_file = root.join("deploy-to-staging.toml");
+        let twin_file = root.join(format!(
+            "deploy-to-staging-{}.toml",
+            &twin.id.simple().to_string()[..8]
+        ));
+        assert!(deploy_file.exists() && twin_file.exists());
+
+        // Another program edits one file; saving a change to another still works.
+        fs::write(&twin_file, fs::read_to_string(&twin_file).unwrap() + "\n").unwrap();
+        let mut renamed = logs.clone();
+        renamed.title = "Pod logs".to_string();
+        files.save(&root, [&deploy, &twin, &renamed], None).unwrap();
+        assert!(fs::read_to_string(root.join("logs.toml"))
+            .unwrap()
+            .contains("Pod logs"));
+
+        // Deleting the edited one is a conflict.
+        assert!(matches!(
+            files.save(&root, [&deploy, &renamed], None),
+            Err(SnippetStoreError::Conflict(_))
+        ));
+
+        let (_, loaded) = SnippetDir::load(&root).unwrap();
+        assert_eq!(loaded.snippets.len(), 3);
+        assert!(loaded.damaged.is_empty() && !loaded.migrated);
+    }
+}
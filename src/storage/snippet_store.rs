@@ -1,7 +1,9 @@
 #![allow(dead_code)]
+use super::library::{discover_project_libraries, Library, Recovery, Scope, GLOBAL_LIBRARY};
+use super::{Backups, Config, Synced};
 use crate::models::Snippet;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
 use std::path::PathBuf;
 use thiserror::Error;
 use uuid::Uuid;
@@ -14,42 +16,208 @@ pub enum SnippetStoreError {
     WriteError(#[source] std::io::Error),
     #[error("Failed to parse snippets: {0}")]
     ParseError(#[source] serde_json::Error),
+    #[error("Failed to parse snippets: {0}")]
+    TomlError(#[source] toml::de::Error),
+    #[error("Failed to write snippets: {0}")]
+    TomlWriteError(#[source] toml::ser::Error),
+    #[error("Snippet library not found: {0}")]
+    LibraryNotFound(String),
+    #[error("Snippet library '{0}' is read-only")]
+    ReadOnly(String),
+    #[error("Failed to read config: {0}")]
+    ConfigError(#[source] std::io::Error),
     #[error("Snippet not found: {0}")]
     SnippetNotFound(String),
+    #[error("Failed to lock snippets file: {0}")]
+    LockError(#[source] std::io::Error),
+    #[error("{} was changed by another program", .0.display())]
+    Conflict(PathBuf),
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-struct SnippetStoreData {
-    snippets: Vec<Snippet>,
+impl SnippetStoreError {
+    /// The file was read but isn't a valid snippet library.
+    pub fn is_corrupt(&self) -> bool {
+        matches!(self, Self::ParseError(_) | Self::TomlError(_))
+    }
 }
 
+/// One mutation of the snippet library, kept as a value so it can be
+/// re-applied on top of a freshly reloaded file after a conflict.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SnippetChange {
+    Add(#[serde(with = "with_library")] Snippet),
+    Update(#[serde(with = "with_library")] Snippet),
+    Delete(Uuid),
+    /// Several changes saved together, such as an import.
+    Batch(Vec<SnippetChange>),
+}
+
+/// A snippet in a saved change keeps the library it is headed for, which
+/// library files leave out.
+mod with_library {
+    use crate::models::Snippet;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct Borrowed<'a> {
+        library: &'a str,
+        snippet: &'a Snippet,
+    }
+
+    #[derive(Deserialize)]
+    struct Owned {
+        library: String,
+        snippet: Snippet,
+    }
+
+    pub fn serialize<S: Serializer>(snippet: &Snippet, serializer: S) -> Result<S::Ok, S::Error> {
+        Borrowed {
+            library: &snippet.library,
+            snippet,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Snippet, D::Error> {
+        let Owned {
+            library,
+            mut snippet,
+        } = Owned::deserialize(deserializer)?;
+        snippet.library = library;
+        Ok(snippet)
+    }
+}
+
+/// Every snippet library in use: the global one in the data directory, those
+/// registered in the config file, and any project libraries found from the
+/// working directory.
 pub struct SnippetStore {
-    snippets: HashMap<Uuid, Snippet>,
-    data_path: PathBuf,
+    libraries: Vec<Library>,
+    /// Libraries that were damaged when the store was opened.
+    recoveries: Vec<Recovery>,
+    /// Problems that didn't stop a library from loading, such as an old
+    /// file that couldn't be upgraded.
+    warnings: Vec<String>,
 }
 
 impl SnippetStore {
     pub fn new() -> Result<Self, SnippetStoreError> {
-        let data_path = Self::get_data_path()?;
+        let mut store = Self::empty();
+        let config = Config::load().map_err(SnippetStoreError::ConfigError)?;
+        let synced = |name: &str| config.synced.iter().any(|s| s == name);
 
-        let store = Self {
-            snippets: HashMap::new(),
-            data_path,
-        };
+        store.open_library(
+            GLOBAL_LIBRARY.to_string(),
+            Self::get_data_path()?,
+            Scope::Global,
+            false,
+            synced(GLOBAL_LIBRARY),
+        )?;
+        if !store.libraries[0].path.exists() && !store.libraries[0].read_only {
+            store.libraries[0].save()?;
+        }
 
-        if store.data_path.exists() {
-            let mut store = store;
-            store.load()?;
-            Ok(store)
-        } else {
-            if let Some(parent) = store.data_path.parent() {
-                fs::create_dir_all(parent).map_err(SnippetStoreError::WriteError)?;
+        for entry in &config.libraries {
+            let name = store.unique_name(entry.name.clone());
+            let sync = synced(&name);
+            store.open_library(
+                name,
+                entry.resolved_path(),
+                Scope::Registered,
+                entry.read_only,
+                sync,
+            )?;
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            for (name, path) in discover_project_libraries(&cwd) {
+                let name = store.unique_name(name);
+                let sync = synced(&name);
+                store.open_library(name, path, Scope::Project, false, sync)?;
+            }
+        }
+        store.upgrade_libraries();
+        Ok(store)
+    }
+
+    /// Rewrites outdated library files in the current format. Project files
+    /// are usually checked in, so they are only rewritten once a snippet in
+    /// them is saved.
+    fn upgrade_libraries(&mut self) {
+        for library in &mut self.libraries {
+            if library.scope == Scope::Project {
+                continue;
             }
-            store.save()?;
-            Ok(store)
+            if let Err(e) = library.upgrade() {
+                self.warnings.push(format!(
+                    "Library '{}' couldn't be upgraded to the current format: {}",
+                    library.name, e
+                ));
+            }
+        }
+    }
+
+    /// A store with no libraries, for when the real one can't be opened.
+    pub fn empty() -> Self {
+        Self {
+            libraries: Vec::new(),
+            recoveries: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
+    #[cfg(test)]
+    pub fn with_libraries(libraries: Vec<Library>) -> Self {
+        Self {
+            libraries,
+            recoveries: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn open_library(
+        &mut self,
+        name: String,
+        path: PathBuf,
+        scope: Scope,
+        read_only: bool,
+        synced: bool,
+    ) -> Result<(), SnippetStoreError> {
+        let backups = Backups::for_library(&name, &path).map_err(SnippetStoreError::ReadError)?;
+        // A file in the middle of a git merge is git's to resolve: it is
+        // opened read-only, so it's neither quarantined nor written.
+        let sync = synced.then(|| Synced::open(&path));
+        let merging = sync.as_ref().is_some_and(|s| s.status().conflicted);
+        let (mut library, recovery) =
+            Library::open_or_recover(name, path, scope, read_only || merging, backups)?;
+        if let Some(sync) = sync {
+            library = library.with_sync(sync);
+        }
+        self.libraries.push(library);
+        self.recoveries.extend(recovery);
+        Ok(())
+    }
+
+    pub fn recoveries(&self) -> &[Recovery] {
+        &self.recoveries
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Puts the quarantined file of `recovery` back in place once the user
+    /// has fixed it.
+    pub fn reinstate(&mut self, recovery: &Recovery) -> Result<(), SnippetStoreError> {
+        let quarantined = recovery
+            .quarantined
+            .as_ref()
+            .ok_or_else(|| SnippetStoreError::ReadOnly(recovery.library.clone()))?;
+        let index = self.library_index(&recovery.library)?;
+        self.libraries[index].reinstate(quarantined, &recovery.path)?;
+        self.recoveries.retain(|r| r.path != recovery.path);
+        Ok(())
+    }
+
     fn get_data_path() -> Result<PathBuf, SnippetStoreError> {
         let base_dirs =
             directories::ProjectDirs::from("com", "sniplias", "sniplias").ok_or_else(|| {
@@ -58,77 +226,336 @@ impl SnippetStore {
                     "Could not find data directory",
                 ))
             })?;
+        // A snippets/ directory, e.g. from `sniplias library split`, or a
+        // snippets.toml from `sniplias convert` takes over from the JSON file.
+        let dir = base_dirs.data_dir().join("snippets");
+        if dir.is_dir() {
+            return Ok(dir);
+        }
+        let toml = base_dirs.data_dir().join("snippets.toml");
+        if toml.exists() {
+            return Ok(toml);
+        }
         Ok(base_dirs.data_dir().join("snippets.json"))
     }
 
-    pub fn load(&mut self) -> Result<(), SnippetStoreError> {
-        let content = fs::read_to_string(&self.data_path).map_err(SnippetStoreError::ReadError)?;
-
-        let data: SnippetStoreData = if content.trim().is_empty() {
-            SnippetStoreData { snippets: vec![] }
-        } else {
-            serde_json::from_str(&content).map_err(SnippetStoreError::ParseError)?
-        };
-
-        self.snippets = data.snippets.into_iter().map(|s| (s.id, s)).collect();
+    /// `name`, or `name~2`, `name~3`... if another library already has it.
+    fn unique_name(&self, name: String) -> String {
+        let taken = |candidate: &str| self.libraries.iter().any(|l| l.name == candidate);
+        if !taken(&name) {
+            return name;
+        }
+        (2..)
+            .map(|n| format!("{}~{}", name, n))
+            .find(|candidate| !taken(candidate))
+            .unwrap()
+    }
 
+    /// Rereads every library from disk.
+    pub fn load(&mut self) -> Result<(), SnippetStoreError> {
+        for library in &mut self.libraries {
+            if library.path.exists() {
+                library.load()?;
+            }
+        }
         Ok(())
     }
 
-    pub fn save(&self) -> Result<(), SnippetStoreError> {
-        let data = SnippetStoreData {
-            snippets: self.snippets.values().cloned().collect(),
-        };
-
-        let content =
-            serde_json::to_string_pretty(&data).map_err(|e| SnippetStoreError::ParseError(e))?;
+    pub fn libraries(&self) -> &[Library] {
+        &self.libraries
+    }
 
-        if let Some(parent) = self.data_path.parent() {
-            fs::create_dir_all(parent).map_err(SnippetStoreError::WriteError)?;
+    /// Picks up how the background commits of synced libraries went.
+    pub fn poll_sync(&mut self) {
+        for library in &mut self.libraries {
+            library.poll_sync();
         }
+    }
 
-        fs::write(&self.data_path, content).map_err(SnippetStoreError::WriteError)?;
+    pub fn library(&self, name: &str) -> Option<&Library> {
+        self.libraries.iter().find(|l| l.name == name)
+    }
 
-        Ok(())
+    fn library_index(&self, name: &str) -> Result<usize, SnippetStoreError> {
+        self.libraries
+            .iter()
+            .position(|l| l.name == name)
+            .ok_or_else(|| SnippetStoreError::LibraryNotFound(name.to_string()))
+    }
+
+    /// Index of the library holding the snippet `id`.
+    fn position_of(&self, id: &Uuid) -> Option<usize> {
+        self.libraries.iter().position(|l| l.get(id).is_some())
     }
 
     pub fn list(&self) -> Vec<&Snippet> {
-        self.snippets.values().collect()
+        self.libraries.iter().flat_map(|l| l.snippets()).collect()
     }
 
     pub fn list_filtered(&self, query: &str) -> Vec<&Snippet> {
-        self.snippets
-            .values()
+        self.libraries
+            .iter()
+            .flat_map(|l| l.snippets())
             .filter(|s| s.matches_search(query))
             .collect()
     }
 
     pub fn get(&self, id: &Uuid) -> Option<&Snippet> {
-        self.snippets.get(id)
+        self.libraries.iter().find_map(|l| l.get(id))
+    }
+
+    /// Applies `change` and saves the libraries it touched. If a save fails
+    /// the in-memory libraries are left as they were, so the caller can
+    /// reload, merge or give up.
+    pub fn apply(&mut self, change: SnippetChange) -> Result<(), SnippetStoreError> {
+        let snapshot: Vec<_> = self
+            .libraries
+            .iter_mut()
+            .map(|l| l.snippets_mut().clone())
+            .collect();
+
+        let message = self.describe(&change);
+        let mut saved = Vec::new();
+        let result = self.apply_in_memory(change).and_then(|touched| {
+            if let Some(&i) = touched.iter().find(|&&i| self.libraries[i].read_only) {
+                return Err(SnippetStoreError::ReadOnly(self.libraries[i].name.clone()));
+            }
+            for &i in &touched {
+                self.libraries[i].save()?;
+                saved.push(i);
+            }
+            Ok(touched)
+        });
+        match result {
+            Ok(touched) => {
+                for i in touched {
+                    self.libraries[i].commit(&message);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                for (library, snippets) in self.libraries.iter_mut().zip(snapshot) {
+                    *library.snippets_mut() = snippets;
+                }
+                // Put back the files already written, so a move that fails
+                // halfway leaves the snippet where it was.
+                for i in saved {
+                    let _ = self.libraries[i].save();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// The change that would take back `change`, worked out before it is
+    /// applied. None if it names a snippet that doesn't exist.
+    pub fn inverse(&self, change: &SnippetChange) -> Option<SnippetChange> {
+        self.inverse_after(change, &mut HashMap::new())
+    }
+
+    /// Like [`SnippetStore::inverse`], with `after` holding the snippets as
+    /// the earlier steps of a batch left them (None once deleted), so each
+    /// step is undone against that rather than the libraries on disk.
+    fn inverse_after(
+        &self,
+        change: &SnippetChange,
+        after: &mut HashMap<Uuid, Option<Snippet>>,
+    ) -> Option<SnippetChange> {
+        let current = |after: &HashMap<Uuid, Option<Snippet>>, id: &Uuid| match after.get(id) {
+            Some(snippet) => snippet.clone(),
+            None => self.get(id).cloned(),
+        };
+        Some(match change {
+            SnippetChange::Add(snippet) => {
+                let inverse = match current(after, &snippet.id) {
+                    Some(existing) => SnippetChange::Update(existing),
+                    None => SnippetChange::Delete(snippet.id),
+                };
+                let mut added = snippet.clone();
+                if added.library.is_empty() {
+                    added.library = GLOBAL_LIBRARY.to_string();
+                }
+                after.insert(snippet.id, Some(added));
+                inverse
+            }
+            SnippetChange::Update(snippet) => {
+                let old = current(after, &snippet.id)?;
+                let mut updated = snippet.clone();
+                if updated.library.is_empty() {
+                    updated.library = old.library.clone();
+                }
+                after.insert(snippet.id, Some(updated));
+                SnippetChange::Update(old)
+            }
+            SnippetChange::Delete(id) => {
+                let old = current(after, id)?;
+                after.insert(*id, None);
+                SnippetChange::Add(old)
+            }
+            SnippetChange::Batch(changes) => {
+                let mut inverses = changes
+                    .iter()
+                    .map(|c| self.inverse_after(c, after))
+                    .collect::<Option<Vec<_>>>()?;
+                inverses.reverse();
+                SnippetChange::Batch(inverses)
+            }
+        })
+    }
+
+    /// A commit message for `change`, worded from the libraries as they are
+    /// before it is applied.
+    fn describe(&self, change: &SnippetChange) -> String {
+        match change {
+            SnippetChange::Add(snippet) => format!("Add snippet '{}'", snippet.title),
+            SnippetChange::Update(snippet) => match self.position_of(&snippet.id) {
+                Some(i)
+                    if !snippet.library.is_empty() && self.libraries[i].name != snippet.library =>
+                {
+                    format!("Move snippet '{}' to '{}'", snippet.title, snippet.library)
+                }
+                _ => format!("Update snippet '{}'", snippet.title),
+            },
+            SnippetChange::Delete(id) => format!(
+                "Delete snippet '{}'",
+                self.get(id).map_or("unknown", |s| s.title.as_str())
+            ),
+            SnippetChange::Batch(changes) => {
+                if let [change] = changes.as_slice() {
+                    return self.describe(change);
+                }
+                let mut counts = [0; 3];
+                count_changes(changes, &mut counts);
+                let kinds = [
+                    ("Add", "added"),
+                    ("Update", "updated"),
+                    ("Delete", "deleted"),
+                ];
+                let parts: Vec<_> = counts.iter().zip(kinds).filter(|(&n, _)| n > 0).collect();
+                match parts.as_slice() {
+                    [(n, (verb, _))] => format!("{} {} snippets", verb, n),
+                    parts => format!(
+                        "Change {} snippets ({})",
+                        counts.iter().sum::<usize>(),
+                        parts
+                            .iter()
+                            .map(|(n, (_, done))| format!("{} {}", n, done))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Reloads the files and replays `change` on top of whatever is there now.
+    pub fn merge(&mut self, change: SnippetChange) -> Result<(), SnippetStoreError> {
+        match self.reload_and_rebase(change)? {
+            Some(change) => self.apply(change),
+            None => Ok(()),
+        }
+    }
+
+    /// Reloads the files and adjusts `change` to them, without applying it.
+    /// None if nothing of it is left to make.
+    pub fn reload_and_rebase(
+        &mut self,
+        change: SnippetChange,
+    ) -> Result<Option<SnippetChange>, SnippetStoreError> {
+        self.load()?;
+        Ok(self.rebase(change))
+    }
+
+    /// `change` adjusted to the libraries as they are now: updates of
+    /// snippets deleted meanwhile become adds, deletes of them are dropped.
+    fn rebase(&self, change: SnippetChange) -> Option<SnippetChange> {
+        match change {
+            SnippetChange::Update(snippet) if self.position_of(&snippet.id).is_none() => {
+                Some(SnippetChange::Add(snippet))
+            }
+            SnippetChange::Delete(id) if self.position_of(&id).is_none() => None,
+            SnippetChange::Batch(changes) => Some(SnippetChange::Batch(
+                changes.into_iter().filter_map(|c| self.rebase(c)).collect(),
+            )),
+            change => Some(change),
+        }
+    }
+
+    /// Applies `change` to the in-memory libraries and returns the indices
+    /// of those that need saving. New snippets go to the library they name,
+    /// or the global one.
+    fn apply_in_memory(&mut self, change: SnippetChange) -> Result<Vec<usize>, SnippetStoreError> {
+        match change {
+            SnippetChange::Add(mut snippet) => {
+                if snippet.library.is_empty() {
+                    snippet.library = GLOBAL_LIBRARY.to_string();
+                }
+                let index = self.library_index(&snippet.library)?;
+                self.libraries[index].insert(snippet);
+                Ok(vec![index])
+            }
+            SnippetChange::Update(mut snippet) => {
+                let from = self
+                    .position_of(&snippet.id)
+                    .ok_or_else(|| SnippetStoreError::SnippetNotFound(snippet.id.to_string()))?;
+                let to = if snippet.library.is_empty() {
+                    from
+                } else {
+                    self.library_index(&snippet.library)?
+                };
+                snippet.library = self.libraries[to].name.clone();
+                // Edited in place keeps its position in the file; moved is appended.
+                if from != to {
+                    self.libraries[from].remove(&snippet.id);
+                }
+                self.libraries[to].insert(snippet);
+                // The destination is saved first: should the source then fail,
+                // the snippet is in two libraries rather than none.
+                Ok(if from == to { vec![to] } else { vec![to, from] })
+            }
+            SnippetChange::Delete(id) => {
+                let from = self
+                    .position_of(&id)
+                    .ok_or_else(|| SnippetStoreError::SnippetNotFound(id.to_string()))?;
+                self.libraries[from].remove(&id);
+                Ok(vec![from])
+            }
+            SnippetChange::Batch(changes) => {
+                let mut touched = Vec::new();
+                for change in changes {
+                    for index in self.apply_in_memory(change)? {
+                        if !touched.contains(&index) {
+                            touched.push(index);
+                        }
+                    }
+                }
+                Ok(touched)
+            }
+        }
     }
 
     pub fn add(&mut self, snippet: Snippet) -> Result<(), SnippetStoreError> {
-        self.snippets.insert(snippet.id, snippet);
-        self.save()
+        self.apply(SnippetChange::Add(snippet))
     }
 
     pub fn update(&mut self, id: Uuid, snippet: Snippet) -> Result<(), SnippetStoreError> {
-        if !self.snippets.contains_key(&id) {
-            return Err(SnippetStoreError::SnippetNotFound(id.to_string()));
-        }
-        self.snippets.insert(id, snippet);
-        self.save()
+        self.apply(SnippetChange::Update(snippet.with_id(id)))
     }
 
     pub fn delete(&mut self, id: &Uuid) -> Result<(), SnippetStoreError> {
-        if self.snippets.remove(id).is_none() {
-            return Err(SnippetStoreError::SnippetNotFound(id.to_string()));
-        }
-        self.save()
+        self.apply(SnippetChange::Delete(*id))
     }
+}
 
-    pub fn data_path(&self) -> &PathBuf {
-        &self.data_path
+/// Adds, updates and deletes in `changes`, including nested batches.
+fn count_changes(changes: &[SnippetChange], counts: &mut [usize; 3]) {
+    for change in changes {
+        match change {
+            SnippetChange::Add(_) => counts[0] += 1,
+            SnippetChange::Update(_) => counts[1] += 1,
+            SnippetChange::Delete(_) => counts[2] += 1,
+            SnippetChange::Batch(changes) => count_changes(changes, counts),
+        }
     }
 }
 
@@ -137,3 +564,234 @@ impl Default for SnippetStore {
         Self::new().expect("Failed to initialize SnippetStore")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_project_snippets_are_written_back_to_their_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("src/deep");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            repo.join(".sniplias.toml"),
+            "[[snippets]]\ntitle = \"Deploy\"\ncommand = \"make deploy\"\n",
+        )
+        .unwrap();
+
+        let found = discover_project_libraries(&nested);
+        assert_eq!(found, [("repo".to_string(), repo.join(".sniplias.toml"))]);
+
+        let global = dir.path().join("snippets.json");
+        let mut store = SnippetStore::with_libraries(vec![
+            Library::open(GLOBAL_LIBRARY.into(), global.clone(), Scope::Global).unwrap(),
+            Library::open("repo".into(), found[0].1.clone(), Scope::Project).unwrap(),
+        ]);
+
+        let deploy = store.list()[0].clone();
+        assert_eq!(deploy.library, "repo");
+        let mut edited = deploy.clone();
+        edited.command = "make deploy ENV={{env:staging}}".to_string();
+        edited.library = String::new();
+        store.apply(SnippetChange::Update(edited)).unwrap();
+        store
+            .add(Snippet::new("Hi".into(), "echo hi".into()))
+            .unwrap();
+
+        let project = fs::read_to_string(repo.join(".sniplias.toml")).unwrap();
+        assert!(project.contains("ENV={{env:staging}}"));
+        assert!(!project.contains("echo hi"));
+        assert!(fs::read_to_string(&global).unwrap().contains("echo hi"));
+    }
+
+    #[test]
+    fn test_project_libraries_are_not_upgraded_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = "[[snippets]]\ntitle = \"Deploy\"\ncommand = \"make deploy\"\n";
+        let project = dir.path().join(".sniplias.toml");
+        let global = dir.path().join("snippets.toml");
+        fs::write(&project, old).unwrap();
+        fs::write(&global, old).unwrap();
+
+        let mut store = SnippetStore::with_libraries(vec![
+            Library::open(GLOBAL_LIBRARY.into(), global.clone(), Scope::Global).unwrap(),
+            Library::open("repo".into(), project.clone(), Scope::Project).unwrap(),
+        ]);
+        store.upgrade_libraries();
+        assert!(store.warnings().is_empty());
+        assert_eq!(fs::read_to_string(&project).unwrap(), old);
+        assert_ne!(fs::read_to_string(&global).unwrap(), old);
+    }
+
+    #[test]
+    fn test_saving_keeps_file_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snippets.json");
+        let mut store = SnippetStore::with_libraries(vec![Library::open(
+            GLOBAL_LIBRARY.into(),
+            path.clone(),
+            Scope::Global,
+        )
+        .unwrap()]);
+        for title in ["zeta", "alpha", "mid", "beta"] {
+            store
+                .add(Snippet::new(title.into(), format!("echo {}", title)))
+                .unwrap();
+        }
+        let before = fs::read_to_string(&path).unwrap();
+
+        let mut edited = store.list()[2].clone();
+        edited.command = "echo middle".to_string();
+        store.apply(SnippetChange::Update(edited)).unwrap();
+        let after = fs::read_to_string(&path).unwrap();
+
+        let titles: Vec<_> = store.list().iter().map(|s| s.title.clone()).collect();
+        assert_eq!(titles, ["zeta", "alpha", "mid", "beta"]);
+        let changed: Vec<_> = before
+            .lines()
+            .zip(after.lines())
+            .filter(|(a, b)| a != b)
+            .collect();
+        assert_eq!(changed.len(), 1, "{:?}", changed);
+        assert!(changed[0].1.contains("echo middle"));
+    }
+
+    #[test]
+    fn test_inverse_takes_changes_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = |name: &str| {
+            let path = dir.path().join(format!("{}.json", name));
+            Library::open(name.into(), path, Scope::Registered).unwrap()
+        };
+        let mut store = SnippetStore::with_libraries(vec![open(GLOBAL_LIBRARY), open("team")]);
+        let mut deploy = Snippet::new("Deploy".into(), "make deploy".into());
+        deploy.library = "team".to_string();
+        store.apply(SnippetChange::Add(deploy.clone())).unwrap();
+
+        let mut moved = store.get(&deploy.id).unwrap().clone();
+        moved.library = GLOBAL_LIBRARY.to_string();
+        for change in [
+            SnippetChange::Update(moved),
+            SnippetChange::Delete(deploy.id),
+        ] {
+            let undo = store.inverse(&change).unwrap();
+            store.apply(change).unwrap();
+            store.apply(undo).unwrap();
+            let restored = store.get(&deploy.id).unwrap();
+            assert_eq!(restored.library, "team");
+            assert_eq!(restored.command, "make deploy");
+        }
+        assert!(store
+            .inverse(&SnippetChange::Delete(Uuid::new_v4()))
+            .is_none());
+    }
+
+    #[test]
+    fn test_inverse_of_a_batch_touching_one_snippet_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snippets.json");
+        let library = Library::open(GLOBAL_LIBRARY.into(), path, Scope::Global).unwrap();
+        let mut store = SnippetStore::with_libraries(vec![library]);
+        let kept = Snippet::new("Kept".into(), "true".into());
+        store.add(kept.clone()).unwrap();
+
+        let deploy = Snippet::new("Deploy".into(), "make deploy".into());
+        let mut edited = deploy.clone();
+        edited.command = "make deploy-prod".to_string();
+        let scratch = Snippet::new("Scratch".into(), "false".into());
+        let mut kept_edited = kept.clone();
+        kept_edited.command = "echo kept".to_string();
+        let change = SnippetChange::Batch(vec![
+            SnippetChange::Add(deploy.clone()),
+            SnippetChange::Update(edited),
+            SnippetChange::Add(scratch.clone()),
+            SnippetChange::Delete(scratch.id),
+            SnippetChange::Update(kept_edited),
+            SnippetChange::Delete(kept.id),
+        ]);
+
+        let undo = store.inverse(&change).unwrap();
+        store.apply(change).unwrap();
+        assert!(store.get(&kept.id).is_none());
+        store.apply(undo).unwrap();
+        assert!(store.get(&deploy.id).is_none());
+        assert!(store.get(&scratch.id).is_none());
+        assert_eq!(store.get(&kept.id).unwrap().command, "true");
+    }
+
+    #[test]
+    fn test_failed_move_leaves_the_snippet_where_it_was() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(format!("{}.json", name));
+        let open = |name: &str| Library::open(name.into(), path(name), Scope::Registered).unwrap();
+        let mut store = SnippetStore::with_libraries(vec![open(GLOBAL_LIBRARY), open("team")]);
+        let mut deploy = Snippet::new("Deploy".into(), "make deploy".into());
+        deploy.library = "team".to_string();
+        store.apply(SnippetChange::Add(deploy.clone())).unwrap();
+
+        // Someone else edits the source library, so saving it fails after
+        // the destination was written.
+        let edited = fs::read_to_string(path("team")).unwrap() + "\n";
+        fs::write(path("team"), &edited).unwrap();
+        let mut moved = deploy.clone();
+        moved.library = GLOBAL_LIBRARY.to_string();
+        assert!(matches!(
+            store.apply(SnippetChange::Update(moved)),
+            Err(SnippetStoreError::Conflict(_))
+        ));
+
+        assert_eq!(store.get(&deploy.id).unwrap().library, "team");
+        assert_eq!(fs::read_to_string(path("team")).unwrap(), edited);
+        assert!(!fs::read_to_string(path(GLOBAL_LIBRARY))
+            .unwrap()
+            .contains("make deploy"));
+    }
+
+    #[test]
+    fn test_synced_library_is_committed_on_save() {
+        let git = |dir: &std::path::Path, args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .output()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        if !git(dir.path(), &["init", "--quiet"]).is_ok_and(|o| o.status.success()) {
+            return;
+        }
+        git(dir.path(), &["config", "user.name", "Test"]).unwrap();
+        git(dir.path(), &["config", "user.email", "test@example.com"]).unwrap();
+
+        let path = dir.path().join("snippets.json");
+        let library = Library::open("team".into(), path.clone(), Scope::Registered)
+            .unwrap()
+            .with_sync(Synced::open(&path));
+        let mut store = SnippetStore::with_libraries(vec![library]);
+
+        let mut deploy = Snippet::new("Deploy".into(), "make deploy".into());
+        deploy.library = "team".to_string();
+        let mut other = Snippet::new("Other".into(), "true".into());
+        other.library = "team".to_string();
+        store.add(deploy.clone()).unwrap();
+        store.libraries[0].wait_for_sync();
+        store
+            .apply(SnippetChange::Batch(vec![
+                SnippetChange::Add(other),
+                SnippetChange::Delete(deploy.id),
+            ]))
+            .unwrap();
+        store.libraries[0].wait_for_sync();
+
+        let log = git(dir.path(), &["log", "--format=%s"]).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&log.stdout),
+            "Change 2 snippets (1 added, 1 deleted)\nAdd snippet 'Deploy'\n"
+        );
+        let status = store.libraries()[0].sync().unwrap().status();
+        assert_eq!(status.error, None);
+    }
+}
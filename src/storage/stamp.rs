@@ -0,0 +1,35 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+/// What a file looked like when we last read or wrote it.
+///
+/// Content is hashed rather than trusting modification times, which are
+/// coarse on some filesystems and restored by some editors and sync tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    len: usize,
+    hash: u64,
+}
+
+impl FileStamp {
+    pub fn new(contents: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        Self {
+            len: contents.len(),
+            hash: hasher.finish(),
+        }
+    }
+
+    /// Stamp of the file as it is on disk now, or `None` if it doesn't exist.
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(contents) => Ok(Some(Self::new(&contents))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Snippet libraries kept in git working trees: committed on every save,
+//! pulled and pushed on request. Shells out to the local `git`.
+
+use crate::utils::output_detached;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long a git command may run, fetches and pushes included, before it
+/// is killed.
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("Failed to run git: {0}")]
+    Spawn(#[source] io::Error),
+    #[error("{} is not inside a git working tree", .0.display())]
+    NotARepo(PathBuf),
+    #[error("git {command} failed: {message}")]
+    Git { command: String, message: String },
+    #[error(
+        "Pulling conflicts with your changes to {}, which were left as they were. \
+         Merge them with git in {}, then push.",
+        .files.join(", "),
+        .root.display()
+    )]
+    Conflict { root: PathBuf, files: Vec<String> },
+    #[error("The remote has commits you don't have yet; run `sniplias sync pull` first")]
+    Rejected,
+}
+
+/// Where a synced library stands relative to its upstream, as of the last
+/// fetch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncStatus {
+    /// Local commits not pushed yet.
+    pub ahead: usize,
+    /// Fetched commits not pulled yet.
+    pub behind: usize,
+    /// Unmerged files in the working tree.
+    pub conflicted: bool,
+    pub has_upstream: bool,
+    /// Why the last commit or status check failed.
+    pub error: Option<String>,
+}
+
+impl SyncStatus {
+    /// Whether the library needs the user's attention.
+    pub fn is_problem(&self) -> bool {
+        self.conflicted || self.error.is_some() || (self.ahead > 0 && self.behind > 0)
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(error) = &self.error {
+            return error.clone();
+        }
+        if self.conflicted {
+            return "conflict".to_string();
+        }
+        if !self.has_upstream {
+            return "no upstream".to_string();
+        }
+        match (self.ahead, self.behind) {
+            (0, 0) => "up to date".to_string(),
+            (ahead, 0) => format!("↑{}", ahead),
+            (0, behind) => format!("↓{}", behind),
+            (ahead, behind) => format!("diverged ↑{} ↓{}", ahead, behind),
+        }
+    }
+}
+
+/// The git working tree holding a library file.
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    root: PathBuf,
+}
+
+impl GitRepo {
+    /// The working tree `path` is in; the file itself may not exist yet.
+    pub fn discover(path: &Path) -> Result<Self, SyncError> {
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let output = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+        if !output.status.success() {
+            return Err(SyncError::NotARepo(path.to_path_buf()));
+        }
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Self { root: root.into() })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, SyncError> {
+        let output = run_git(&self.root, args)?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Err(SyncError::Git {
+                command: args[0].to_string(),
+                message: if stderr.trim().is_empty() {
+                    stdout
+                } else {
+                    stderr
+                }
+                .trim()
+                .to_string(),
+            })
+        }
+    }
+
+    /// Commits `paths` alone with `message`: files, or directories with
+    /// everything added and removed in them. Returns false if they had no
+    /// changes to commit.
+    pub fn commit(&self, paths: &[&Path], message: &str) -> Result<bool, SyncError> {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        self.git(&[&["add", "--all", "--"], &paths[..]].concat())?;
+        if self
+            .git(&[&["diff", "--cached", "--quiet", "--"], &paths[..]].concat())
+            .is_ok()
+        {
+            return Ok(false);
+        }
+        self.git(&[&["commit", "--quiet", "-m", message, "--"], &paths[..]].concat())?;
+        Ok(true)
+    }
+
+    pub fn status(&self) -> Result<SyncStatus, SyncError> {
+        let output = self.git(&["status", "--porcelain=v2", "--branch"])?;
+        let mut status = SyncStatus::default();
+        for line in output.lines() {
+            // Only reported once the upstream branch exists.
+            if let Some(counts) = line.strip_prefix("# branch.ab ") {
+                status.has_upstream = true;
+                for count in counts.split_whitespace() {
+                    if let Some(ahead) = count.strip_prefix('+') {
+                        status.ahead = ahead.parse().unwrap_or(0);
+                    } else if let Some(behind) = count.strip_prefix('-') {
+                        status.behind = behind.parse().unwrap_or(0);
+                    }
+                }
+            } else if line.starts_with("u ") {
+                status.conflicted = true;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Updates what is known of the upstream, without touching the working tree.
+    pub fn fetch(&self) -> Result<(), SyncError> {
+        self.git(&["fetch", "--quiet"]).map(|_| ())
+    }
+
+    /// Fetches and rebases local commits on top. If they conflict, the
+    /// rebase is aborted so the working tree stays as it was.
+    pub fn pull(&self) -> Result<(), SyncError> {
+        match self.git(&["pull", "--rebase", "--autostash", "--quiet"]) {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                let unmerged = self
+                    .git(&["diff", "--name-only", "--diff-filter=U"])
+                    .unwrap_or_default();
+                let files: Vec<String> = unmerged.lines().map(str::to_string).collect();
+                if files.is_empty() {
+                    return Err(error);
+                }
+                // Best effort: a failed abort still leaves the conflict to report.
+                let _ = self.git(&["rebase", "--abort"]);
+                Err(SyncError::Conflict {
+                    root: self.root.clone(),
+                    files,
+                })
+            }
+        }
+    }
+
+    pub fn push(&self) -> Result<(), SyncError> {
+        self.git(&["push", "--quiet"])
+            .map(|_| ())
+            .map_err(|e| match e {
+                SyncError::Git { message, .. }
+                    if message.contains("[rejected]") || message.contains("non-fast-forward") =>
+                {
+                    SyncError::Rejected
+                }
+                e => e,
+            })
+    }
+}
+
+/// Runs git in `dir` without a terminal, so it fails rather than prompts
+/// for credentials.
+fn run_git(dir: &Path, args: &[&str]) -> Result<Output, SyncError> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0");
+    output_detached(command, TIMEOUT).map_err(SyncError::Spawn)
+}
+
+enum Job {
+    Commit(PathBuf, String),
+    /// Answered once every commit queued before it is done.
+    #[cfg(test)]
+    Flush(Sender<()>),
+}
+
+/// Commits one working tree in order on a thread of its own, so a slow git
+/// never holds up the UI. Dropping it waits for the queued commits.
+#[derive(Debug)]
+struct Committer {
+    jobs: Option<Sender<Job>>,
+    /// The status after each commit, or why it failed.
+    results: Mutex<Receiver<Result<SyncStatus, String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Committer {
+    fn start(repo: GitRepo) -> Self {
+        let (jobs, queue) = mpsc::channel();
+        let (done, results) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            for job in queue {
+                match job {
+                    Job::Commit(path, message) => {
+                        let result = repo
+                            .commit(&[&path], &message)
+                            .and_then(|_| repo.status())
+                            .map_err(|e| e.to_string());
+                        let _ = done.send(result);
+                    }
+                    #[cfg(test)]
+                    Job::Flush(reply) => {
+                        let _ = reply.send(());
+                    }
+                }
+            }
+        });
+        Self {
+            jobs: Some(jobs),
+            results: Mutex::new(results),
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, job: Job) -> bool {
+        self.jobs
+            .as_ref()
+            .is_some_and(|jobs| jobs.send(job).is_ok())
+    }
+}
+
+impl Drop for Committer {
+    fn drop(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A library that is committed on every save, and what was last seen of its
+/// working tree.
+#[derive(Debug, Clone)]
+pub struct Synced {
+    repo: Option<GitRepo>,
+    status: SyncStatus,
+    committer: Option<Arc<Committer>>,
+}
+
+impl Synced {
+    pub fn open(path: &Path) -> Self {
+        let mut synced = match GitRepo::discover(path) {
+            Ok(repo) => Self {
+                committer: Some(Arc::new(Committer::start(repo.clone()))),
+                repo: Some(repo),
+                status: SyncStatus::default(),
+            },
+            Err(e) => Self {
+                repo: None,
+                status: SyncStatus {
+                    error: Some(e.to_string()),
+                    ..SyncStatus::default()
+                },
+                committer: None,
+            },
+        };
+        synced.refresh();
+        synced
+    }
+
+    pub fn repo(&self) -> Option<&GitRepo> {
+        self.repo.as_ref()
+    }
+
+    pub fn status(&self) -> &SyncStatus {
+        &self.status
+    }
+
+    /// Rereads the status from git.
+    pub fn refresh(&mut self) {
+        if let Some(repo) = &self.repo {
+            self.status = repo.status().unwrap_or_else(|e| SyncStatus {
+                error: Some(e.to_string()),
+                ..SyncStatus::default()
+            });
+        }
+    }
+
+    /// Queues a commit of `path`, which takes the file as it is when the
+    /// commit runs, later saves included. A failure doesn't undo the save;
+    /// it is kept in the status for the user to see once [`Synced::poll`]
+    /// picks it up.
+    pub fn commit(&mut self, path: &Path, message: &str) {
+        let Some(committer) = &self.committer else {
+            return;
+        };
+        if !committer.send(Job::Commit(path.to_path_buf(), message.to_string())) {
+            self.status.error = Some("The git committer stopped".to_string());
+        }
+    }
+
+    /// Takes in the outcome of the commits finished since the last call.
+    pub fn poll(&mut self) {
+        let Some(committer) = &self.committer else {
+            return;
+        };
+        let results = committer.results.lock().unwrap_or_else(|e| e.into_inner());
+        for result in results.try_iter() {
+            match result {
+                Ok(status) => self.status = status,
+                Err(error) => self.status.error = Some(error),
+            }
+        }
+    }
+
+    /// Waits for the queued commits, then polls.
+    #[cfg(test)]
+    pub fn wait(&mut self) {
+        if let Some(committer) = &self.committer {
+            let (reply, done) = mpsc::channel();
+            if committer.send(Job::Flush(reply)) {
+                let _ = done.recv();
+            }
+        }
+        self.poll();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn clone(remote: &Path, to: &Path) -> GitRepo {
+        git(
+            remote.parent().unwrap(),
+            &[
+                "clone",
+                "--quiet",
+                remote.to_str().unwrap(),
+                to.to_str().unwrap(),
+            ],
+        );
+        git(to, &["config", "user.name", "Test"]);
+        git(to, &["config", "user.email", "test@example.com"]);
+        GitRepo::discover(&to.join("snippets.json")).unwrap()
+    }
+
+    #[test]
+    fn test_sync_through_a_bare_repo() {
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        git(dir.path(), &["init", "--quiet", "--bare", "remote.git"]);
+        let alice = clone(&remote, &dir.path().join("alice"));
+        let file = |repo: &GitRepo| repo.root().join("snippets.json");
+
+        fs::write(file(&alice), "[1]\n").unwrap();
+        assert!(alice.commit(&[&file(&alice)], "Add snippet 'one'").unwrap());
+        assert!(!alice.commit(&[&file(&alice)], "Nothing").unwrap());
+        git(alice.root(), &["push", "--quiet", "-u", "origin", "HEAD"]);
+        let bob = clone(&remote, &dir.path().join("bob"));
+
+        fs::write(file(&alice), "[2]\n").unwrap();
+        alice
+            .commit(&[&file(&alice)], "Update snippet 'one'")
+            .unwrap();
+        alice.push().unwrap();
+
+        fs::write(file(&bob), "[3]\n").unwrap();
+        bob.commit(&[&file(&bob)], "Update snippet 'one'").unwrap();
+        assert!(matches!(bob.push(), Err(SyncError::Rejected)));
+        match bob.pull() {
+            Err(SyncError::Conflict { files, .. }) => assert_eq!(files, ["snippets.json"]),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(file(&bob)).unwrap(), "[3]\n");
+
+        let status = bob.status().unwrap();
+        assert!(!status.conflicted);
+        assert_eq!((status.ahead, status.behind), (1, 1));
+        assert_eq!(status.describe(), "diverged ↑1 ↓1");
+    }
+}
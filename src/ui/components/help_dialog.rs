@@ -68,7 +68,17 @@ pub fn render_help_dialog(f: &mut Frame, theme: &Theme) {
         ("a", "Add new"),
         ("e", "Edit"),
         ("d", "Delete"),
-        ("Enter", "Run snippet"),
+        ("Ctrl+z/r", "Undo / redo the last change"),
+        ("i", "Import aliases from live shell"),
+        ("l", "Filter snippets by library"),
+        ("m / c", "Move / copy snippet to a library"),
+        (
+            "Enter",
+            "Run snippet / edit PATH list / snippet from history",
+        ),
+        ("v", "Snippet from history, with variables"),
+        ("Alt+Enter", "New line in function body"),
+        ("Alt+↑/↓", "Reorder PATH entries"),
         ("Esc", "Cancel"),
         ("?", "Toggle help"),
         ("q", "Quit"),
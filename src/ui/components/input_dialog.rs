@@ -13,6 +13,7 @@ pub struct InputField {
     pub value: String,
     pub cursor_pos: usize,
     pub focused: bool,
+    pub multiline: bool,
 }
 
 impl InputField {
@@ -22,6 +23,18 @@ impl InputField {
             value: String::new(),
             cursor_pos: 0,
             focused: false,
+            multiline: false,
+        }
+    }
+
+    pub fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self
+    }
+
+    pub fn handle_newline(&mut self) {
+        if self.multiline {
+            self.handle_char('\n');
         }
     }
 
@@ -69,6 +82,9 @@ pub struct InputDialog {
     pub mode: DialogMode,
     pub message: Option<(String, bool)>,
     pub update_info: Option<(String, String)>, // (local_version, remote_version)
+    pub preview: Option<String>,
+    /// First preview line shown, for previews taller than the dialog.
+    pub scroll: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -79,6 +95,13 @@ pub enum DialogMode {
     Run,
     Input,
     Update,
+    Conflict,
+    Review,
+    PathEdit,
+    Import,
+    Move,
+    Copy,
+    Recovery,
 }
 
 impl InputDialog {
@@ -90,9 +113,18 @@ impl InputDialog {
             mode,
             message: None,
             update_info: None,
+            preview: None,
+            scroll: 0,
         }
     }
 
+    /// Whether the focused field takes several lines (Alt+Enter).
+    pub fn in_multiline_field(&self) -> bool {
+        self.fields
+            .get(self.current_field)
+            .is_some_and(|field| field.multiline)
+    }
+
     pub fn add_field(mut self, label: impl Into<String>) -> Self {
         self.fields.push(InputField::new(label));
         self.update_focus();
@@ -109,6 +141,44 @@ impl InputDialog {
         self
     }
 
+    pub fn add_multiline_field_with_value(
+        mut self,
+        label: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.fields
+            .push(InputField::new(label).with_value(value).multiline());
+        self.update_focus();
+        self
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>, is_error: bool) -> Self {
+        self.message = Some((message.into(), is_error));
+        self
+    }
+
+    pub fn with_preview(mut self, preview: impl Into<String>) -> Self {
+        self.preview = Some(preview.into());
+        self
+    }
+
+    /// Whether the dialog shows a preview instead of a form.
+    pub fn has_preview(&self) -> bool {
+        matches!(
+            self.mode,
+            DialogMode::Review | DialogMode::Import | DialogMode::Recovery
+        )
+    }
+
+    /// Scrolls the preview by `lines`, stopping at its first and last line.
+    pub fn scroll_preview(&mut self, lines: isize) {
+        let total = self.preview.as_deref().map_or(0, |p| p.lines().count());
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines)
+            .min(total.saturating_sub(1));
+    }
+
     pub fn with_update_info(mut self, local: &str, remote: &str, _install_method: &str) -> Self {
         self.update_info = Some((local.to_string(), remote.to_string()));
         // Add the confirmation field
@@ -135,6 +205,26 @@ impl InputDialog {
         }
     }
 
+    /// Swaps the current field's value with the one `offset` places away and
+    /// follows it, so list entries can be reordered in place.
+    pub fn move_field(&mut self, offset: isize) {
+        let Some(target) = self.current_field.checked_add_signed(offset) else {
+            return;
+        };
+        if target >= self.fields.len() {
+            return;
+        }
+        let (a, b) = (
+            self.current_field.min(target),
+            self.current_field.max(target),
+        );
+        let (left, right) = self.fields.split_at_mut(b);
+        std::mem::swap(&mut left[a].value, &mut right[0].value);
+        std::mem::swap(&mut left[a].cursor_pos, &mut right[0].cursor_pos);
+        self.current_field = target;
+        self.update_focus();
+    }
+
     pub fn update_focus(&mut self) {
         for (i, field) in self.fields.iter_mut().enumerate() {
             field.focused = i == self.current_field;
@@ -175,15 +265,13 @@ impl InputDialog {
 
 pub fn render_input_dialog(f: &mut Frame, dialog: &InputDialog, theme: &Theme) {
     let area = f.area();
-    let popup_area = dialog.centered_rect(
-        60,
-        if dialog.mode == DialogMode::Update {
-            40
-        } else {
-            60
-        },
-        area,
-    );
+    let popup_area = match dialog.mode {
+        DialogMode::Update => dialog.centered_rect(60, 40, area),
+        DialogMode::Review | DialogMode::Import | DialogMode::Recovery => {
+            dialog.centered_rect(80, 80, area)
+        }
+        _ => dialog.centered_rect(60, 60, area),
+    };
 
     f.render_widget(Clear, popup_area);
 
@@ -204,10 +292,22 @@ pub fn render_input_dialog(f: &mut Frame, dialog: &InputDialog, theme: &Theme) {
         return;
     }
 
+    if dialog.has_preview() {
+        render_review_dialog(f, dialog, theme, inner_area);
+        return;
+    }
+
     let constraints: Vec<Constraint> = dialog
         .fields
         .iter()
-        .map(|_| Constraint::Length(3))
+        .map(|field| {
+            if field.multiline {
+                let lines = field.value.lines().count().clamp(3, 8) as u16;
+                Constraint::Length(lines + 2)
+            } else {
+                Constraint::Length(3)
+            }
+        })
         .chain(std::iter::once(Constraint::Min(1)))
         .collect();
 
@@ -295,7 +395,7 @@ fn render_update_dialog(f: &mut Frame, dialog: &InputDialog, theme: &Theme, area
 
     // Show install method message
     let method_text = if let Some(_field) = dialog.fields.first() {
-        format!("Installed manually. Run update? Press y to confirm.")
+        "Installed manually. Run update? Press y to confirm.".to_string()
     } else {
         "".to_string()
     };
@@ -338,3 +438,98 @@ fn render_update_dialog(f: &mut Frame, dialog: &InputDialog, theme: &Theme, area
         f.render_widget(paragraph, chunks[2]);
     }
 }
+
+fn render_review_dialog(f: &mut Frame, dialog: &InputDialog, theme: &Theme, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(if dialog.message.is_some() { 1 } else { 0 }),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let lines: Vec<Line> = dialog
+        .preview
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .map(|line| {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Style::default()
+                    .fg(theme.text_primary)
+                    .add_modifier(ratatui::style::Modifier::BOLD)
+            } else if line.starts_with('+') {
+                Style::default().fg(theme.success)
+            } else if line.starts_with('-') {
+                Style::default().fg(theme.error)
+            } else if line.starts_with("@@") {
+                Style::default().fg(theme.gradient_start)
+            } else {
+                Style::default().fg(theme.text_secondary)
+            };
+            Line::styled(line.to_string(), style)
+        })
+        .collect();
+
+    // Long diffs scroll rather than being cut off, with a line saying so.
+    let total = lines.len();
+    let mut preview_area = chunks[0];
+    if total > preview_area.height as usize {
+        preview_area.height -= 1;
+        let height = preview_area.height as usize;
+        let first = dialog.scroll.min(total.saturating_sub(height));
+        let position = format!(
+            "Lines {}-{} of {}  (↑/↓, PgUp/PgDn to scroll)",
+            first + 1,
+            (first + height).min(total),
+            total
+        );
+        let footer = Rect {
+            y: preview_area.y + preview_area.height,
+            height: 1,
+            ..preview_area
+        };
+        f.render_widget(
+            Paragraph::new(position).style(Style::default().fg(theme.text_secondary)),
+            footer,
+        );
+        f.render_widget(
+            Paragraph::new(lines).scroll((first.min(u16::MAX as usize) as u16, 0)),
+            preview_area,
+        );
+    } else {
+        f.render_widget(Paragraph::new(lines), preview_area);
+    }
+
+    if let Some((msg, is_error)) = &dialog.message {
+        let style = if *is_error {
+            theme.error_style()
+        } else {
+            theme.success_style()
+        };
+        f.render_widget(Paragraph::new(msg.as_str()).style(style), chunks[1]);
+    }
+
+    if let Some(field) = dialog.fields.first() {
+        let field_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.border_style(field.focused))
+            .border_type(theme.border_type())
+            .title(ratatui::text::Span::styled(
+                format!(" {} ", field.label),
+                Style::default()
+                    .fg(theme.gradient_start)
+                    .add_modifier(ratatui::style::Modifier::BOLD),
+            ));
+
+        let prefix = &field.value[..field.cursor_pos];
+        let suffix = &field.value[field.cursor_pos..];
+        let paragraph = Paragraph::new(format!("{}|{}", prefix, suffix))
+            .style(Style::default().fg(theme.gradient_start))
+            .block(field_block);
+
+        f.render_widget(paragraph, chunks[2]);
+    }
+}
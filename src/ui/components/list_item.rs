@@ -1,7 +1,7 @@
 use ratatui::{
     layout::Rect,
     style::Style,
-    text::Line,
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem as RatatuiListItem, ListState},
     Frame,
 };
@@ -11,6 +11,16 @@ use super::super::Theme;
 pub trait Listable {
     fn title(&self) -> &str;
     fn subtitle(&self) -> Option<&str>;
+
+    /// A short tag shown after the title, such as an alias kind.
+    fn badge(&self) -> Option<&str> {
+        None
+    }
+
+    /// Marks the entry with a warning sign when set.
+    fn warning(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub fn render_list<T: Listable>(
@@ -36,7 +46,18 @@ pub fn render_list<T: Listable>(
             let prefix = if is_selected { "> " } else { "  " };
             let title_text = format!("{}{}", prefix, item.title());
 
-            let mut lines = vec![Line::styled(title_text, style)];
+            let mut title_line = Line::styled(title_text, style);
+            if let Some(badge) = item.badge() {
+                title_line.push_span(Span::styled(
+                    format!(" [{}]", badge),
+                    style.fg(theme.gradient_end),
+                ));
+            }
+            if item.warning().is_some() {
+                title_line.push_span(Span::styled(" ⚠", style.fg(theme.error)));
+            }
+
+            let mut lines = vec![title_line];
 
             if let Some(subtitle) = item.subtitle() {
                 let subtitle_style = if is_selected {
@@ -4,6 +4,7 @@ use super::super::Theme;
 use crate::ui::Tab;
 use crate::utils::UpdateInfo;
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_status_bar(
     f: &mut Frame,
     area: Rect,
@@ -11,21 +12,47 @@ pub fn render_status_bar(
     current_tab: Tab,
     is_searching: bool,
     is_dialog_open: bool,
+    is_multiline_field: bool,
     is_help_visible: bool,
     update_info: &UpdateInfo,
+    sync: Option<(String, bool)>,
+    message: Option<(&str, bool)>,
 ) {
     let shortcuts = if is_help_visible {
         vec![("Esc", "Close")]
     } else if is_dialog_open {
-        vec![("Tab", "Next"), ("Enter", "Confirm"), ("Esc", "Cancel")]
+        let mut base = vec![("Tab", "Next"), ("Enter", "Confirm"), ("Esc", "Cancel")];
+        if is_multiline_field {
+            base.insert(1, ("Alt+Enter", "Newline"));
+        }
+        base
     } else if is_searching {
         vec![("Enter/Esc", "Exit"), ("q", "Quit")]
     } else {
         match current_tab {
-            Tab::Aliases => {
+            Tab::Aliases | Tab::Functions => {
+                let mut base = vec![
+                    ("←/→  j/k", "Navigate"),
+                    ("/", "Search"),
+                    ("a", "Add"),
+                    ("e", "Edit"),
+                    ("d", "Delete"),
+                    ("?", "Help"),
+                    ("q", "Quit"),
+                ];
+                if current_tab == Tab::Aliases {
+                    base.insert(5, ("i", "Import"));
+                }
+                if update_info.update_available {
+                    base.insert(base.len() - 1, ("u", "Update"));
+                }
+                base
+            }
+            Tab::Env => {
                 let mut base = vec![
                     ("←/→  j/k", "Navigate"),
                     ("/", "Search"),
+                    ("Enter", "Edit list"),
                     ("a", "Add"),
                     ("e", "Edit"),
                     ("d", "Delete"),
@@ -37,6 +64,20 @@ pub fn render_status_bar(
                 }
                 base
             }
+            Tab::History => {
+                let mut base = vec![
+                    ("←/→  j/k", "Navigate"),
+                    ("/", "Search"),
+                    ("Enter", "New snippet"),
+                    ("v", "With variables"),
+                    ("?", "Help"),
+                    ("q", "Quit"),
+                ];
+                if update_info.update_available {
+                    base.insert(base.len() - 1, ("u", "Update"));
+                }
+                base
+            }
             Tab::Snippets => {
                 let mut base = vec![
                     ("←/→  j/k", "Navigate"),
@@ -45,6 +86,8 @@ pub fn render_status_bar(
                     ("a", "Add"),
                     ("e", "Edit"),
                     ("d", "Delete"),
+                    ("m/c", "Move/Copy"),
+                    ("l", "Library"),
                     ("?", "Help"),
                     ("q", "Quit"),
                 ];
@@ -57,6 +100,33 @@ pub fn render_status_bar(
     };
 
     let mut spans = Vec::new();
+    // Synced libraries go first so a long row of shortcuts can't push them
+    // off screen; in the error colour when one needs attention.
+    if let Some((text, problem)) = sync {
+        spans.push(ratatui::text::Span::styled(
+            text,
+            Style::default().fg(if problem {
+                theme.error
+            } else {
+                theme.text_secondary
+            }),
+        ));
+        spans.push(ratatui::text::Span::styled(
+            " | ",
+            Style::default().fg(theme.border),
+        ));
+    }
+    // The outcome of the last action, e.g. what was undone, stands in for
+    // the shortcuts until the next key press.
+    let shortcuts = if let Some((text, is_error)) = message {
+        spans.push(ratatui::text::Span::styled(
+            text.to_string(),
+            Style::default().fg(if is_error { theme.error } else { theme.success }),
+        ));
+        Vec::new()
+    } else {
+        shortcuts
+    };
     for (i, (key, action)) in shortcuts.iter().enumerate() {
         if i > 0 {
             spans.push(ratatui::text::Span::styled(
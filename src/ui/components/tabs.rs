@@ -10,33 +10,44 @@ use ratatui::{
 pub enum Tab {
     Aliases,
     Snippets,
+    Functions,
+    Env,
+    History,
 }
 
 impl Tab {
+    const ALL: [Tab; 5] = [
+        Tab::Snippets,
+        Tab::Aliases,
+        Tab::Functions,
+        Tab::Env,
+        Tab::History,
+    ];
+
     pub fn titles() -> Vec<&'static str> {
-        vec!["Snippets", "Aliases"]
+        vec!["Snippets", "Aliases", "Functions", "Env", "History"]
     }
 
     pub fn index(self) -> usize {
         match self {
             Tab::Snippets => 0,
             Tab::Aliases => 1,
+            Tab::Functions => 2,
+            Tab::Env => 3,
+            Tab::History => 4,
         }
     }
 
     pub fn from_index(index: usize) -> Self {
-        match index {
-            0 => Tab::Snippets,
-            _ => Tab::Aliases,
-        }
+        Self::ALL[index % Self::ALL.len()]
     }
 
     pub fn next(self) -> Self {
-        Self::from_index((self.index() + 1) % 2)
+        Self::from_index(self.index() + 1)
     }
 
     pub fn prev(self) -> Self {
-        Self::from_index(if self.index() == 0 { 1 } else { 0 })
+        Self::from_index(self.index() + Self::ALL.len() - 1)
     }
 }
 
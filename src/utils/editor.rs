@@ -0,0 +1,18 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// Opens `path` in `$VISUAL`, `$EDITOR` or `vi` and waits for it to exit.
+/// Run through `sh` so editors configured with arguments, like
+/// `code --wait`, work.
+pub fn open_in_editor(path: &Path) -> io::Result<ExitStatus> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+}
@@ -1,5 +1,11 @@
+mod detached;
+mod editor;
+mod shadow;
 mod terminal;
 mod update;
 
+pub use detached::output_detached;
+pub use editor::open_in_editor;
+pub use shadow::ShadowIndex;
 pub use terminal::Terminal;
 pub use update::{InstallMethod, UpdateInfo};
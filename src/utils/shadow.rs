@@ -0,0 +1,191 @@
+use crate::models::{Alias, AliasSource};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Builtins of bash and zsh that an alias of the same name would hide.
+const BUILTINS: &[&str] = &[
+    ".", ":", "alias", "bg", "bind", "break", "builtin", "caller", "cd", "command", "compgen",
+    "complete", "continue", "declare", "dirs", "disown", "echo", "enable", "eval", "exec", "exit",
+    "export", "false", "fc", "fg", "getopts", "hash", "help", "history", "jobs", "kill", "let",
+    "local", "logout", "popd", "printf", "pushd", "pwd", "read", "readonly", "return", "set",
+    "shift", "shopt", "source", "suspend", "test", "times", "trap", "true", "type", "typeset",
+    "ulimit", "umask", "unalias", "unset", "wait", "autoload", "bindkey", "compdef", "emulate",
+    "print", "setopt", "unsetopt", "whence", "where", "which", "zle", "zmodload", "zstyle",
+];
+
+/// Shell startup files, relative to the home directory, that commonly hold
+/// aliases besides the one sniplias manages.
+const RC_FILES: &[&str] = &[
+    ".bashrc",
+    ".bash_aliases",
+    ".bash_profile",
+    ".profile",
+    ".zshrc",
+    ".zsh_aliases",
+    ".zshenv",
+    ".zprofile",
+];
+
+/// What an alias name would hide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shadow {
+    /// Also defined as an alias in another rc file.
+    Alias(PathBuf),
+    Builtin,
+    Executable(PathBuf),
+}
+
+/// Executables on `$PATH` and aliases in other rc files, gathered once so
+/// every alias in the list can be checked cheaply.
+#[derive(Debug, Default)]
+pub struct ShadowIndex {
+    home: Option<PathBuf>,
+    executables: HashMap<String, PathBuf>,
+    other_aliases: HashMap<String, PathBuf>,
+}
+
+impl ShadowIndex {
+    /// Scans `$PATH` and the usual rc files, skipping `managed`, the file
+    /// sniplias itself edits.
+    pub fn scan(managed: &Path) -> Self {
+        let home = directories::BaseDirs::new().map(|d| d.home_dir().to_path_buf());
+        let managed = managed
+            .canonicalize()
+            .unwrap_or_else(|_| managed.to_path_buf());
+
+        let mut other_aliases = HashMap::new();
+        for path in home
+            .iter()
+            .flat_map(|h| RC_FILES.iter().map(move |f| h.join(f)))
+        {
+            let Ok(real) = path.canonicalize() else {
+                continue;
+            };
+            if real == managed {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&real) else {
+                continue;
+            };
+            let source = if path
+                .file_name()
+                .is_some_and(|f| f.to_string_lossy().starts_with(".z"))
+            {
+                AliasSource::Zsh
+            } else {
+                AliasSource::Bash
+            };
+            for alias in content
+                .lines()
+                .filter_map(|line| Alias::parse_line(line, source.clone()))
+            {
+                other_aliases
+                    .entry(alias.name)
+                    .or_insert_with(|| path.clone());
+            }
+        }
+
+        Self {
+            home,
+            executables: path_executables(),
+            other_aliases,
+        }
+    }
+
+    /// The first thing `name` would shadow, checking other rc files, then
+    /// builtins, then `$PATH`.
+    pub fn check(&self, name: &str) -> Option<Shadow> {
+        if let Some(path) = self.other_aliases.get(name) {
+            return Some(Shadow::Alias(path.clone()));
+        }
+        if BUILTINS.contains(&name) {
+            return Some(Shadow::Builtin);
+        }
+        self.executables
+            .get(name)
+            .map(|path| Shadow::Executable(path.clone()))
+    }
+
+    /// A one-line warning such as "`ls` shadows /usr/bin/ls".
+    pub fn warning(&self, name: &str) -> Option<String> {
+        let what = match self.check(name)? {
+            Shadow::Alias(path) => format!("already defined in {}", self.tilde(&path)),
+            Shadow::Builtin => "shadows a shell builtin".to_string(),
+            Shadow::Executable(path) => format!("shadows {}", path.display()),
+        };
+        Some(format!("`{}` {}", name, what))
+    }
+
+    fn tilde(&self, path: &Path) -> String {
+        match self.home.as_deref().and_then(|h| path.strip_prefix(h).ok()) {
+            Some(rest) => format!("~/{}", rest.display()),
+            None => path.display().to_string(),
+        }
+    }
+}
+
+/// The first executable of each name on `$PATH`, as the shell would find it.
+fn path_executables() -> HashMap<String, PathBuf> {
+    let mut found = HashMap::new();
+    let Some(path) = std::env::var_os("PATH") else {
+        return found;
+    };
+    let mut seen_dirs = HashSet::new();
+    for dir in std::env::split_paths(&path) {
+        if !seen_dirs.insert(dir.clone()) {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if found.contains_key(&name) || !is_executable(&entry.path()) {
+                continue;
+            }
+            found.insert(name, entry.path());
+        }
+    }
+    found
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_prefers_other_rc_files() {
+        let index = ShadowIndex {
+            home: Some(PathBuf::from("/home/me")),
+            executables: HashMap::from([
+                ("ls".to_string(), PathBuf::from("/usr/bin/ls")),
+                ("gs".to_string(), PathBuf::from("/usr/bin/gs")),
+            ]),
+            other_aliases: HashMap::from([(
+                "gs".to_string(),
+                PathBuf::from("/home/me/.bash_aliases"),
+            )]),
+        };
+        assert_eq!(index.warning("ls").unwrap(), "`ls` shadows /usr/bin/ls");
+        assert_eq!(
+            index.warning("gs").unwrap(),
+            "`gs` already defined in ~/.bash_aliases"
+        );
+        assert_eq!(index.check("cd"), Some(Shadow::Builtin));
+        assert_eq!(index.warning("xyz"), None);
+    }
+}